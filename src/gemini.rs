@@ -0,0 +1,479 @@
+use std::{
+    future::{self, Future},
+    io,
+    path::Path,
+    pin::{pin, Pin},
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
+
+use rustls::{
+    ServerConfig,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
+};
+use tokio::{
+    io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _},
+    net::TcpStream,
+    sync::Semaphore,
+};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+
+use crate::{
+    prelude::*,
+    syntax::{
+        content::{
+            self,
+            render::gemtext::{encode, Gemtext},
+        },
+        serial::populate_graph,
+    },
+    types::{Graph, Node},
+};
+
+// A request is an absolute URL of at most 1024 bytes followed by CRLF
+const MAX_REQUEST_LENGTH: u64 = 1026;
+
+// How long a client has to finish the TLS handshake and send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Connections handled at once, beyond which new ones wait to be accepted
+const MAX_CONNECTIONS: usize = 256;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u8,
+    pub meta: String,
+    pub body: String,
+}
+
+impl Response {
+    fn success(body: String, language: &str) -> Response {
+        let meta = if language.is_empty() {
+            String::from("text/gemini; charset=utf-8")
+        } else {
+            format!("text/gemini; charset=utf-8; lang={language}")
+        };
+
+        Response {
+            status: 20,
+            meta,
+            body,
+        }
+    }
+
+    fn failure(status: u8, meta: &str) -> Response {
+        Response {
+            status,
+            meta: meta.to_owned(),
+            body: String::new(),
+        }
+    }
+
+    pub fn header(&self) -> String {
+        format!("{} {}\r\n", self.status, self.meta)
+    }
+}
+
+/// Serves the graph as Gemtext over TLS until `stop` completes, then stops
+/// accepting connections and waits for those already open to finish.
+///
+/// # Errors
+/// Fails if the certificate or key can't be loaded, or if binding to or
+/// accepting connections on `address` fails.
+pub async fn serve(
+    address: &str,
+    cert: &Path,
+    key: &Path,
+    stop: impl Future<Output = ()> + Send,
+) -> io::Result<()> {
+    let acceptor = make_acceptor(cert, key)?;
+    let listener = tokio::net::TcpListener::bind(address).await?;
+
+    log!(
+        "Gemini listening on {}",
+        listener
+            .local_addr()
+            .map_or("<unknown>".to_string(), |s| s.to_string())
+    );
+
+    let permits = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    let mut stopped = pin!(stop);
+    loop {
+        let next = async {
+            let permit = Arc::clone(&permits)
+                .acquire_owned()
+                .await
+                .map_err(io::Error::other)?;
+            listener.accept().await.map(|accepted| (permit, accepted))
+        };
+        let Some(accepted) = unless_stopped(stopped.as_mut(), next).await
+        else {
+            break;
+        };
+        let (permit, (stream, peer)) = accepted?;
+        let connection_acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(&connection_acceptor, stream).await {
+                log!("Gemini request from {peer} failed: {e}");
+            }
+            drop(permit);
+        });
+    }
+
+    // Every permit is free again once open connections are done
+    if let Ok(count) = u32::try_from(MAX_CONNECTIONS) {
+        drop(permits.acquire_many(count).await);
+    }
+    log!("Stopped serving Gemini");
+    Ok(())
+}
+
+// Runs `next` to completion, or gives up on it if `stop` completes first
+async fn unless_stopped<T>(
+    mut stop: Pin<&mut impl Future<Output = ()>>,
+    next: impl Future<Output = T>,
+) -> Option<T> {
+    let mut running = pin!(next);
+    future::poll_fn(|context| {
+        if stop.as_mut().poll(context).is_ready() {
+            Poll::Ready(None)
+        } else {
+            running.as_mut().poll(context).map(Some)
+        }
+    })
+    .await
+}
+
+fn make_acceptor(cert: &Path, key: &Path) -> io::Result<TlsAcceptor> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|e| {
+            io::Error::other(format!(
+                "Failed to read certificate {}: {e}",
+                cert.display()
+            ))
+        })?;
+
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(|e| {
+        io::Error::other(format!("Failed to read key {}: {e}", key.display()))
+    })?;
+
+    let config = ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(io::Error::other)?
+    .with_no_client_auth()
+    .with_single_cert(certs, private_key)
+    .map_err(io::Error::other)?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+async fn handle(acceptor: &TlsAcceptor, stream: TcpStream) -> io::Result<()> {
+    let (mut tls, request) =
+        tokio::time::timeout(REQUEST_TIMEOUT, read_request(acceptor, stream))
+            .await
+            .map_err(|_elapsed| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "Timed out waiting for the request",
+                )
+            })??;
+
+    let response = respond(&String::from_utf8_lossy(&request));
+    tls.write_all(response.header().as_bytes()).await?;
+    tls.write_all(response.body.as_bytes()).await?;
+    tls.shutdown().await
+}
+
+async fn read_request(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+) -> io::Result<(TlsStream<TcpStream>, Vec<u8>)> {
+    let mut tls = acceptor.accept(stream).await?;
+
+    let mut request = Vec::new();
+    tokio::io::BufReader::new(&mut tls)
+        .take(MAX_REQUEST_LENGTH)
+        .read_until(b'\n', &mut request)
+        .await?;
+
+    Ok((tls, request))
+}
+
+pub fn respond(request: &str) -> Response {
+    let Some(url) = request.strip_suffix("\r\n") else {
+        return Response::failure(59, "Request must end with CRLF");
+    };
+
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Response::failure(59, "Request must be an absolute URL");
+    };
+
+    if !scheme.eq_ignore_ascii_case("gemini") {
+        return Response::failure(53, "Only gemini:// URLs are served");
+    }
+
+    let path = rest
+        .split(['?', '#'])
+        .next()
+        .and_then(|s| s.split_once('/'))
+        .map_or(String::from("/"), |(_host, path)| format!("/{path}"));
+
+    route(&populate_graph(), &decode(&path))
+}
+
+fn route(graph: &Graph, path: &str) -> Response {
+    let language = &graph.meta.config.content_language;
+
+    if path == "/" {
+        Response::success(index(graph), language)
+    } else if path == "/tree" && graph.meta.config.tree {
+        Response::success(tree(graph), language)
+    } else if let Some(id) = path.strip_prefix("/node/") {
+        if !graph.nodes.contains_key(id)
+            && let Some(key) = graph.lowercase_keymap.get(id)
+        {
+            Response::failure(31, &format!("/node/{}", encode(key)))
+        } else if let Some(node) = graph.nodes.get(id) {
+            Response::success(node_page(graph, node), language)
+        } else {
+            Response::failure(51, &format!("Could not find node ID {id}"))
+        }
+    } else {
+        Response::failure(51, "Not found")
+    }
+}
+
+fn index(graph: &Graph) -> String {
+    let config = &graph.meta.config;
+    let mut lines = vec![
+        if config.site_title.is_empty() {
+            String::from("# en")
+        } else {
+            format!("# {}", config.site_title)
+        },
+        String::new(),
+        if config.site_description.is_empty() {
+            String::from("A non-linear writing instrument.")
+        } else {
+            config.site_description.clone()
+        },
+    ];
+
+    if config.index_root_node
+        && let Some(root) = graph.get_root()
+    {
+        lines.push(String::new());
+        lines.push(link(&root.id, &format!("Root: {}", root.title)));
+    }
+
+    if config.index_node_list {
+        lines.push(String::new());
+        lines.push(String::from("## Nodes"));
+        lines.extend(
+            listed_nodes(graph)
+                .into_iter()
+                .filter(|node| node.id != graph.root_node)
+                .take(config.index_node_count.into())
+                .map(|node| link(&node.id, &node.title)),
+        );
+    }
+
+    if config.tree {
+        lines.push(String::new());
+        lines.push(String::from("=> /tree Tree"));
+    }
+
+    lines.join("\n")
+}
+
+fn tree(graph: &Graph) -> String {
+    let nodes = listed_nodes(graph);
+    let mut lines = vec![
+        String::from("# Tree"),
+        String::new(),
+        format!("Total nodes: {}", graph.nodes.len()),
+        String::new(),
+    ];
+
+    for node in nodes {
+        lines.push(link(&node.id, &node.title));
+        for edge in node.connections.iter().flatten() {
+            if !edge.detached {
+                lines.push(link(&edge.to, &format!("  » {}", edge.to)));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn node_page(graph: &Graph, node: &Node) -> String {
+    let mut lines = vec![
+        format!("# {}", node.title),
+        String::new(),
+        content::render(&node.text, &mut Gemtext::default())
+            .trim()
+            .to_owned(),
+    ];
+
+    let outgoing = node.connections.clone().unwrap_or_default();
+    let incoming = graph.incoming.get(&node.id).cloned().unwrap_or_default();
+
+    if !outgoing.is_empty() {
+        lines.push(String::new());
+        lines.push(String::from("## Connections"));
+        lines.extend(outgoing.iter().map(|edge| {
+            if edge.detached {
+                format!("{} » {}", node.id, edge.to)
+            } else {
+                link(&edge.to, &edge.to)
+            }
+        }));
+    }
+
+    if !incoming.is_empty() {
+        lines.push(String::new());
+        lines.push(String::from("## Incoming connections"));
+        lines.extend(incoming.iter().map(|edge| link(&edge.from, &edge.from)));
+    }
+
+    lines.join("\n")
+}
+
+// Visible nodes, ordered by ID so pages are stable across requests
+fn listed_nodes(graph: &Graph) -> Vec<Node> {
+    let mut nodes: Vec<Node> = graph
+        .nodes
+        .values()
+        .filter(|node| !node.hidden)
+        .cloned()
+        .collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    nodes
+}
+
+fn link(id: &str, label: &str) -> String {
+    format!("=> /node/{} {label}", encode(id))
+}
+
+fn decode(path: &str) -> String {
+    let mut decoded: Vec<u8> = Vec::with_capacity(path.len());
+    let mut bytes = path.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let digits: Vec<u8> = bytes.clone().take(2).collect();
+            if digits.len() == 2
+                && let Ok(hex) = std::str::from_utf8(&digits)
+                && let Ok(value) = u8::from_str_radix(hex, 16)
+            {
+                decoded.push(value);
+                bytes.nth(1);
+                continue;
+            }
+        }
+        decoded.push(byte);
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stopping() {
+        let mut ready = pin!(future::ready(()));
+        assert_eq!(
+            unless_stopped(ready.as_mut(), future::ready(1)).await,
+            None
+        );
+
+        let mut pending = pin!(future::pending::<()>());
+        assert_eq!(
+            unless_stopped(pending.as_mut(), future::ready(1)).await,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn index_lists_root() {
+        let response = respond("gemini://localhost/\r\n");
+        assert_eq!(response.status, 20);
+        assert!(response.body.contains("=> /node/Documentation Root:"));
+        assert!(!response.body.contains("=> /node/Test "));
+    }
+
+    #[test]
+    fn index_without_path() {
+        let response = respond("gemini://localhost\r\n");
+        assert_eq!(response.status, 20);
+        assert!(response.body.starts_with("# "));
+    }
+
+    #[test]
+    fn node_page() {
+        let response = respond("gemini://localhost/node/TOML\r\n");
+        assert_eq!(response.status, 20);
+        assert!(response.meta.contains("lang=en"));
+        assert!(response.body.starts_with("# TOML\n"));
+        assert!(response.body.contains("=> https://toml.io https://toml.io"));
+        assert!(response.body.contains("=> /node/en en"));
+    }
+
+    #[test]
+    fn tree_page() {
+        let response = respond("gemini://localhost/tree?q#f\r\n");
+        assert_eq!(response.status, 20);
+        assert!(response.body.contains("=> /node/Graph Graph"));
+    }
+
+    #[test]
+    fn redirect() {
+        let response = respond("gemini://localhost/node/syntax\r\n");
+        assert_eq!(response, Response::failure(31, "/node/Syntax"));
+    }
+
+    #[test]
+    fn not_found() {
+        let response = respond("gemini://localhost/node/G4zLqnT0Xv\r\n");
+        assert_eq!(response.status, 51);
+        assert_eq!(respond("gemini://localhost/nowhere\r\n").status, 51);
+    }
+
+    #[test]
+    fn bad_requests() {
+        assert_eq!(respond("gemini://localhost/").status, 59);
+        assert_eq!(respond("/node/Syntax\r\n").status, 59);
+        assert_eq!(respond("https://localhost/\r\n").status, 53);
+    }
+
+    #[test]
+    fn header() {
+        let response = Response::success(String::new(), "");
+        assert_eq!(response.header(), "20 text/gemini; charset=utf-8\r\n");
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(decode("/node/Some%20Node%2"), "/node/Some Node%2");
+        assert_eq!(decode("/%E9%A7%84"), "/駄");
+        assert_eq!(encode("Some Node"), "Some%20Node");
+    }
+
+    #[test]
+    fn missing_certificate() {
+        let error = make_acceptor(
+            Path::new("IxGjq8vN3aT6yK0pRcLsW2bEhU5mZoDf"),
+            Path::new("IxGjq8vN3aT6yK0pRcLsW2bEhU5mZoDf"),
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("Failed to read certificate"));
+    }
+}
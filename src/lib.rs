@@ -6,6 +6,8 @@ pub mod prelude {
 
 pub mod types;
 pub mod router;
+pub mod gemini;
+pub mod listen;
 pub mod syntax;
 pub mod dev;
 
@@ -0,0 +1,304 @@
+use std::{
+    future::{self, Future},
+    io,
+    pin::pin,
+    task::Poll,
+};
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::fs::FileTypeExt as _,
+    path::{Path, PathBuf},
+};
+
+use axum::Router;
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+use crate::{prelude::*, syntax::command::Arguments};
+
+/// A socket the web server accepts connections on.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    /// A Unix domain socket, along with its path if en created it and so
+    /// should remove it when done.
+    #[cfg(unix)]
+    Unix(UnixListener, Option<PathBuf>),
+}
+
+impl Listener {
+    /// Takes the socket passed by a service manager through `LISTEN_FDS`,
+    /// as in systemd socket activation, or otherwise binds to the path
+    /// given with `--socket`, or to `--hostname` and `--port`.
+    ///
+    /// # Errors
+    /// Fails if the passed socket isn't a listening stream socket, or if
+    /// binding fails.
+    pub async fn bind(args: &Arguments) -> io::Result<Listener> {
+        if let Some(activated) = take_activated()? {
+            return Ok(activated);
+        }
+
+        #[cfg(unix)]
+        if let Some(ref path) = args.socket_path {
+            remove_stale(path)?;
+            let listener = UnixListener::bind(path).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Failed to listen on {}: {e}", path.display()),
+                )
+            })?;
+            return Ok(Listener::Unix(listener, Some(path.clone())));
+        }
+
+        let address = args.make_address();
+        let listener = TcpListener::bind(&address).await.map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to listen on {address}: {e}"),
+            )
+        })?;
+        Ok(Listener::Tcp(listener))
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Listener::Tcp(ref listener) => listener
+                .local_addr()
+                .map_or("<unknown>".to_string(), |s| s.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(ref listener, _) => listener
+                .local_addr()
+                .ok()
+                .and_then(|address| {
+                    address.as_pathname().map(|path| path.display().to_string())
+                })
+                .unwrap_or("<unnamed socket>".to_string()),
+        }
+    }
+}
+
+// The first socket passed by the service manager, if any
+fn take_activated() -> io::Result<Option<Listener>> {
+    let mut fds = listenfd::ListenFd::from_env();
+    if fds.len() > 1 {
+        log!(
+            "Received {} sockets, only the first will be used",
+            fds.len()
+        );
+    }
+
+    // Tried in turn, since taking one of the wrong kind leaves it in place
+    if let Ok(Some(listener)) = fds.take_tcp_listener(0) {
+        listener.set_nonblocking(true)?;
+        return Ok(Some(Listener::Tcp(TcpListener::from_std(listener)?)));
+    }
+
+    #[cfg(unix)]
+    if let Some(listener) = fds.take_unix_listener(0)? {
+        listener.set_nonblocking(true)?;
+        return Ok(Some(Listener::Unix(
+            UnixListener::from_std(listener)?,
+            None,
+        )));
+    }
+
+    Ok(None)
+}
+
+// A socket left behind by an earlier run would make binding fail, but any
+// other kind of file is left alone
+#[cfg(unix)]
+fn remove_stale(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            fs::remove_file(path)
+        },
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "Failed to listen on {}: a file that isn't a socket exists \
+                    there",
+                path.display()
+            ),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Serves `router` until `stop` completes, then stops accepting connections
+/// and waits for requests already being handled to finish.
+///
+/// # Errors
+/// Fails if accepting connections fails.
+pub async fn serve_until(
+    listener: Listener,
+    router: Router,
+    stop: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    log!("Listening on {}", listener.describe());
+
+    match listener {
+        Listener::Tcp(tcp) => {
+            axum::serve(tcp, router)
+                .with_graceful_shutdown(stop)
+                .await?;
+        },
+        #[cfg(unix)]
+        Listener::Unix(unix, created) => {
+            let served =
+                axum::serve(unix, router).with_graceful_shutdown(stop).await;
+            if let Some(path) = created
+                && let Err(e) = fs::remove_file(&path)
+            {
+                log!("Failed to remove socket {}: {e}", path.display());
+            }
+            served?;
+        },
+    }
+
+    log!("Stopped serving");
+    Ok(())
+}
+
+/// Completes when en is asked to stop with SIGINT or SIGTERM.
+pub async fn wait_for_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log!("Failed to listen for SIGINT: {e}");
+            future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            },
+            Err(e) => {
+                log!("Failed to listen for SIGTERM: {e}");
+                future::pending::<()>().await;
+            },
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    // Whichever comes first
+    let mut interrupted = pin!(interrupt);
+    let mut terminated = pin!(terminate);
+    future::poll_fn(|context| {
+        if interrupted.as_mut().poll(context).is_ready()
+            || terminated.as_mut().poll(context).is_ready()
+        {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    log!("Shutting down, waiting for open connections to finish");
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _},
+        sync::oneshot,
+    };
+
+    use crate::syntax::serial::populate_graph;
+
+    use super::*;
+
+    const REQUEST: &[u8] =
+        b"GET /node/Syntax HTTP/1.1\r\nHost: en\r\nConnection: close\r\n\r\n";
+
+    async fn read_response(
+        mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    ) -> String {
+        stream.write_all(REQUEST).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn tcp() {
+        let args = Arguments {
+            hostname: String::from("127.0.0.1"),
+            ..Arguments::new()
+        };
+        let listener = Listener::bind(&args).await.unwrap();
+        let address = listener.describe();
+        let router = crate::router::new(&populate_graph());
+
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_until(listener, router, async {
+            stopped.await.unwrap_or_default();
+        }));
+
+        let stream = tokio::net::TcpStream::connect(&address).await.unwrap();
+        let response = read_response(stream).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(tokio::net::TcpStream::connect(&address).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket() {
+        let path = std::env::temp_dir()
+            .join(format!("en-socket-{}.sock", std::process::id()));
+        let args = Arguments {
+            socket_path: Some(path.clone()),
+            ..Arguments::new()
+        };
+
+        // Left behind as if by an earlier run that didn't stop cleanly
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        let listener = Listener::bind(&args).await.unwrap();
+        assert_eq!(listener.describe(), path.display().to_string());
+        let router = crate::router::new(&populate_graph());
+
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve_until(listener, router, async {
+            stopped.await.unwrap_or_default();
+        }));
+
+        let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+        let response = read_response(stream).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("<h1 class=\"node-title\">Syntax</h1>"));
+
+        stop.send(()).unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn not_a_socket() {
+        let path = std::env::temp_dir()
+            .join(format!("en-not-socket-{}", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let args = Arguments {
+            socket_path: Some(path.clone()),
+            ..Arguments::new()
+        };
+
+        let error = Listener::bind(&args).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(path.is_file());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,14 +1,28 @@
-use std::{backtrace, io, panic};
+use std::{
+    backtrace, fs,
+    io::{self, Write as _},
+    panic,
+    process::ExitCode,
+};
 
-use en::{prelude::*, ONSET, syntax::serial::populate_graph, syntax};
+use tokio::sync::watch;
+
+use en::{
+    listen::{self, Listener},
+    prelude::*,
+    ONSET,
+    syntax::{
+        check::{self, Severity},
+        command::{Arguments, Command, Usage},
+        diff, exchange,
+        serial::{edit, journal, migrate, populate_graph, settings},
+    },
+};
 
 #[tokio::main]
-async fn main() -> io::Result<()> {
+async fn main() -> ExitCode {
     print_debugging_state();
 
-    let args = syntax::command::Arguments::new().parse();
-    let address = args.make_address();
-
     #[allow(clippy::print_stderr)]
     panic::set_hook(Box::new(|info| {
         let payload = info
@@ -28,29 +42,250 @@ async fn main() -> io::Result<()> {
         }
     }));
 
+    let args = match Arguments::new().try_parse() {
+        Ok(args) => args,
+        Err(Usage::Help(text) | Usage::Version(text)) => {
+            return match print(&text) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(_) => ExitCode::FAILURE,
+            };
+        },
+        Err(Usage::Invalid(message)) => {
+            print_error(&format!("{message}\nRun 'en --help' for usage."));
+            return ExitCode::from(2);
+        },
+    };
+
+    if let Err(message) = settings::validate(&args) {
+        print_error(&message);
+        return ExitCode::from(2);
+    }
+
+    match run(&args).await {
+        Ok(code) => code,
+        Err(e) => {
+            print_error(&e.to_string());
+            ExitCode::FAILURE
+        },
+    }
+}
+
+async fn run(args: &Arguments) -> io::Result<ExitCode> {
+    match args.command {
+        Command::Serve => serve(args).await?,
+        Command::Check => return check(args),
+        Command::Build { ref path } => {
+            let graph = populate_graph();
+            en::router::load_templates(args, &graph.meta.config)
+                .map_err(io::Error::other)?;
+            let count =
+                en::router::build::write(&graph, path, args.compress).await?;
+            log!("Built {count} files in {}", path.display());
+        },
+        Command::Export {
+            ref format,
+            ref path,
+        } => {
+            let count = exchange::export(format, &populate_graph(), path)?;
+            log!("Exported {count} nodes to {}", path.display());
+        },
+        Command::Import {
+            ref format,
+            ref paths,
+        } => {
+            let (graph, report) = exchange::import(format, paths)?;
+            for line in report {
+                log!("{line}");
+            }
+            print(&toml::to_string(&graph).map_err(io::Error::other)?)?;
+        },
+        Command::Query { ref text } => query(args, text)?,
+        Command::Mutate(ref mutation) => {
+            edit::save(&args.graph_path, std::slice::from_ref(mutation))?;
+            log!("Applied {mutation} to {}", args.graph_path.display());
+        },
+        Command::Journal { count } => {
+            let entries = journal::read(&args.graph_path)?;
+            let shown = entries.iter().rev().take(count).enumerate();
+            let lines: Vec<String> = shown
+                .map(|(index, entry)| {
+                    format!(
+                        "{:>3}  {:<16}  {}",
+                        index.saturating_add(1),
+                        entry.make_age(),
+                        entry.changes.join(", ")
+                    )
+                })
+                .collect();
+
+            if lines.is_empty() {
+                log!("No edits recorded for {}", args.graph_path.display());
+            } else {
+                print(&format!("{}\n", lines.join("\n")))?;
+            }
+        },
+        Command::Undo { count } => {
+            for entry in journal::undo(&args.graph_path, count)? {
+                log!("Undid {}", entry.changes.join(", "));
+            }
+        },
+        Command::Diff { ref old, ref new } => {
+            let report = diff::compare(&diff::read(old)?, &diff::read(new)?);
+            if args.json {
+                print(&format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&report)
+                        .map_err(io::Error::other)?
+                ))?;
+            } else {
+                print(&report.make_text())?;
+            }
+        },
+        Command::Migrate => {
+            let applied = migrate::rewrite(&args.graph_path)?;
+            if applied.is_empty() {
+                log!(
+                    "{} is already at version {}",
+                    args.graph_path.display(),
+                    migrate::describe(migrate::CURRENT)
+                );
+            }
+            for migration in applied {
+                log!("Applied {migration} to {}", args.graph_path.display());
+            }
+        },
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn serve(args: &Arguments) -> io::Result<()> {
     let graph = populate_graph();
+    en::router::load_templates(args, &graph.meta.config)
+        .map_err(io::Error::other)?;
     let router = en::router::new(&graph);
+    let listener = Listener::bind(args).await?;
 
-    let listener =
-        tokio::net::TcpListener::bind(&address).await.map_err(|e| {
-            log!("Failed to create listener at {address}: {e:#?}");
-            e
-        })?;
-
-    log!(
-        "Listening on {}",
-        listener
-            .local_addr()
-            .map(|s| s.to_string())
-            .unwrap_or("<unknown>".to_string())
-    );
-
-    axum::serve(listener, router).await.map_err(|e| {
+    // Both servers stop on the same signal
+    let (stop, stopped) = watch::channel(false);
+    let signal_stop = stop.clone();
+    tokio::spawn(async move {
+        listen::wait_for_signal().await;
+        signal_stop.send_replace(true);
+    });
+    let until_stopped = |mut receiver: watch::Receiver<bool>| async move {
+        drop(receiver.wait_for(|&is_stopped| is_stopped).await);
+    };
+
+    let gemini = args.make_gemini_address().map(|gemini_address| {
+        let (cert, key) = (args.gemini_cert.clone(), args.gemini_key.clone());
+        let gemini_stop = until_stopped(stopped.clone());
+        tokio::spawn(async move {
+            if let Err(e) =
+                en::gemini::serve(&gemini_address, &cert, &key, gemini_stop)
+                    .await
+            {
+                log!("Failed to serve Gemini at {gemini_address}: {e}");
+            }
+        })
+    });
+
+    let served =
+        listen::serve_until(listener, router, until_stopped(stopped)).await;
+
+    // Also stops Gemini if the web server stopped on its own
+    stop.send_replace(true);
+    if let Some(task) = gemini
+        && let Err(e) = task.await
+    {
+        log!("Gemini server task failed: {e}");
+    }
+
+    served.map_err(|e| {
         log!("Failed to serve application: {e:#?}");
-        io::Error::other(e)
+        e
+    })
+}
+
+// Lists problems in the graph, failing if any of them is an error
+fn check(args: &Arguments) -> io::Result<ExitCode> {
+    let path = &args.graph_path;
+    let source = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to read {}: {e}", path.display()),
+        )
     })?;
+    let graph = migrate::load(&source);
+    let problems = check::inspect(&graph);
+
+    let errors = problems
+        .iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .count();
+    let mut lines: Vec<String> =
+        problems.iter().map(ToString::to_string).collect();
+    lines.push(format!(
+        "Checked {} nodes in {}: {errors} errors, {} warnings",
+        graph.nodes.len(),
+        path.display(),
+        problems.len().saturating_sub(errors)
+    ));
+    print(&format!("{}\n", lines.join("\n")))?;
+
+    Ok(if errors == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn query(args: &Arguments, text: &str) -> io::Result<()> {
+    let graph = populate_graph();
+    let found = graph.search(text, args.hidden);
+
+    if args.json {
+        let listed: Vec<serde_json::Value> = found
+            .iter()
+            .map(|node| {
+                serde_json::json!({
+                    "id": node.id,
+                    "title": node.title,
+                    "summary": node.make_summary(),
+                    "hidden": node.hidden,
+                })
+            })
+            .collect();
+        return print(&format!(
+            "{}\n",
+            serde_json::to_string_pretty(&listed).map_err(io::Error::other)?
+        ));
+    }
+
+    if found.is_empty() {
+        log!("No nodes match {text:?}");
+        return Ok(());
+    }
+
+    let width = found.iter().map(|node| node.id.len()).max().unwrap_or(0);
+    let lines: Vec<String> = found
+        .iter()
+        .map(|node| format!("{:<width$}  {}", node.id, node.title))
+        .collect();
+    print(&format!("{}\n", lines.join("\n")))
+}
+
+// Unlike print!, fails rather than panics when stdout is closed early, as
+// when piped into head
+fn print(output: &str) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()
+}
 
-    Ok(())
+#[allow(clippy::print_stderr)]
+fn print_error(message: &str) {
+    eprintln!("en: {message}");
 }
 
 fn print_debugging_state() {
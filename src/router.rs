@@ -1,9 +1,35 @@
-use axum::{routing::get, Router};
+use axum::{
+    routing::{any, get, post},
+    Router,
+};
 
-use crate::{syntax::serial::Format, types::Graph};
+use crate::{
+    syntax::{command::Arguments, serial::Format},
+    types::{Config, Graph},
+};
 
+pub mod assets;
+pub mod build;
+mod caching;
+pub mod compression;
 mod handlers;
 
+/// Compiles the templates pages are rendered with, including those of the
+/// theme named in `config`, so that errors in them are found before serving
+/// rather than on each request.
+///
+/// # Errors
+/// Fails if the theme doesn't exist or a template can't be read or parsed.
+pub fn load_templates(args: &Arguments, config: &Config) -> Result<(), String> {
+    let layers = assets::Layers::new(args, config)?;
+    handlers::template::init(&layers, args.dev).map_err(|e| {
+        format!(
+            "Failed to load templates: {}",
+            handlers::template::describe(&e)
+        )
+    })
+}
+
 pub fn new(graph: &Graph) -> Router {
     let mut router = Router::new()
         .route(
@@ -11,22 +37,36 @@ pub fn new(graph: &Graph) -> Router {
             get(|| handlers::navigation::page("index.html"))
                 .post(handlers::navigation::search),
         )
-        .route(
-            "/static/style.css",
-            get(|| handlers::fixed::file("./static/style.css", "text/css")),
-        )
-        .route(
-            "/static/favicon.svg",
-            get(|| {
-                handlers::fixed::file("./static/favicon.svg", "image/svg+xml")
-            }),
-        )
+        .route("/static/{*path}", get(handlers::fixed::file))
         .route(
             "/node/{node_id}",
             get(handlers::graph::node).post(handlers::graph::node),
         )
         .fallback(handlers::error::not_found);
 
+    if graph.meta.config.edit {
+        router = router
+            .route(
+                "/node/{node_id}/edit",
+                get(handlers::edit::form).post(handlers::edit::save),
+            )
+            .route("/journal", get(handlers::edit::journal))
+            .route("/journal/undo", post(handlers::edit::undo));
+    }
+
+    if graph.meta.config.api {
+        router = router
+            .route("/api/nodes", get(handlers::api::nodes))
+            .route("/api/nodes/{node_id}", get(handlers::api::node))
+            .route(
+                "/api/nodes/{node_id}/neighbors",
+                get(handlers::api::neighbors),
+            )
+            .route("/api/edges", get(handlers::api::edges))
+            .route("/api", any(handlers::api::not_found))
+            .route("/api/{*path}", any(handlers::api::not_found));
+    }
+
     if graph.meta.config.about {
         router = router
             .route("/about", get(|| handlers::navigation::page("about.html")));
@@ -41,18 +81,20 @@ pub fn new(graph: &Graph) -> Router {
         if graph.meta.config.raw_json {
             router = router.route(
                 "/graph/json",
-                get(|| handlers::fixed::serial(&Format::JSON)),
+                get(|query| handlers::fixed::serial(&Format::JSON, query)),
             );
         }
         if graph.meta.config.raw_toml {
             router = router.route(
                 "/graph/toml",
-                get(|| handlers::fixed::serial(&Format::TOML)),
+                get(|query| handlers::fixed::serial(&Format::TOML, query)),
             );
         }
     }
 
     router
+        .layer(axum::middleware::from_fn(compression::negotiate))
+        .layer(axum::middleware::from_fn(caching::validate))
 }
 
 #[cfg(test)]
@@ -74,9 +116,7 @@ mod tests {
         let default_graph = populate_graph();
         let graph = Graph {
             meta: Meta {
-                config: config
-                    .map(|c| c.to_owned())
-                    .unwrap_or(default_graph.meta.config),
+                config: config.cloned().unwrap_or(default_graph.meta.config),
                 ..default_graph.meta
             },
             ..default_graph
@@ -108,9 +148,14 @@ mod tests {
             "/tree",
             "/node/Syntax",
             "/static/style.css",
+            "/static/theme.css",
             "/static/favicon.svg",
             "/graph/json",
             "/graph/toml",
+            "/api/nodes",
+            "/api/nodes/Syntax",
+            "/api/nodes/Syntax/neighbors",
+            "/api/edges",
         ];
 
         for route in routes {
@@ -119,6 +164,50 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn edit_page() {
+        let config = Config {
+            edit: true,
+            ..populate_graph().meta.config
+        };
+
+        let response = request("/node/Syntax/edit", Some(&config)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let journal = request("/journal", Some(&config)).await;
+        assert_eq!(journal.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn no_edit_page() {
+        let response = request("/node/Syntax/edit", None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let journal = request("/journal", None).await;
+        assert_eq!(journal.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn api_not_found() {
+        for route in ["/api", "/api/nowhere", "/api/nodes/Qz8vLm2N"] {
+            let response = request(route, None).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+            assert_eq!(
+                response.headers().get("content-type").unwrap(),
+                "application/json"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn no_api() {
+        let config = Config {
+            api: false,
+            ..populate_graph().meta.config
+        };
+
+        let response = request("/api/nodes", Some(&config)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn no_about_page() {
         let config = Config {
@@ -0,0 +1,415 @@
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{syntax::command::Arguments, types::Config};
+
+/// Templates built into en, by name.
+pub const TEMPLATES: [(&str, &str); 9] = [
+    ("about.html", include_str!("../../templates/about.html")),
+    ("base.html", include_str!("../../templates/base.html")),
+    ("edit.html", include_str!("../../templates/edit.html")),
+    ("empty.html", include_str!("../../templates/empty.html")),
+    ("error.html", include_str!("../../templates/error.html")),
+    ("index.html", include_str!("../../templates/index.html")),
+    ("journal.html", include_str!("../../templates/journal.html")),
+    ("node.html", include_str!("../../templates/node.html")),
+    ("tree.html", include_str!("../../templates/tree.html")),
+];
+
+/// Static files built into en, by name.
+pub const STATIC: [(&str, &[u8]); 3] = [
+    ("style.css", include_bytes!("../../static/style.css")),
+    ("theme.css", include_bytes!("../../static/theme.css")),
+    ("favicon.svg", include_bytes!("../../static/favicon.svg")),
+];
+
+/// A theme built into en, laid out like the themes in the themes directory.
+pub struct Theme {
+    pub name: &'static str,
+    pub templates: &'static [(&'static str, &'static str)],
+    pub static_files: &'static [(&'static str, &'static [u8])],
+}
+
+pub const THEMES: [Theme; 2] = [
+    Theme {
+        name: "serif",
+        templates: &[],
+        static_files: &[(
+            "theme.css",
+            include_bytes!("../../themes/serif/static/theme.css"),
+        )],
+    },
+    Theme {
+        name: "night",
+        templates: &[],
+        static_files: &[(
+            "theme.css",
+            include_bytes!("../../themes/night/static/theme.css"),
+        )],
+    },
+];
+
+/// Where templates and static files are looked up. Each layer inherits
+/// whatever it doesn't override from the ones below it: en's built-in
+/// files, then the theme built into en, then the theme in the themes
+/// directory and then the directories given with `--templates` and
+/// `--static`.
+#[derive(Clone, Default)]
+pub struct Layers {
+    pub theme: Option<&'static Theme>,
+    /// Template directories, from lowest to highest precedence.
+    pub templates: Vec<PathBuf>,
+    /// Static file directories, from lowest to highest precedence.
+    pub static_dirs: Vec<PathBuf>,
+}
+
+impl Layers {
+    /// Finds the layers for the theme named in `config`, which is looked
+    /// up first in the themes directory and then among the themes built
+    /// into en. An empty name or `default` selects no theme.
+    ///
+    /// # Errors
+    /// Fails if there's no theme by that name in either place.
+    pub fn new(args: &Arguments, config: &Config) -> Result<Layers, String> {
+        let mut layers = Layers::default();
+        let name = config.theme.as_str();
+
+        if !name.is_empty() && name != "default" {
+            let directory = args.themes_path.join(name);
+            let is_directory =
+                !name.contains(['/', '\\', '.']) && directory.is_dir();
+
+            layers.theme = THEMES.iter().find(|theme| theme.name == name);
+            if layers.theme.is_none() && !is_directory {
+                let bundled: Vec<&str> =
+                    THEMES.iter().map(|theme| theme.name).collect();
+                return Err(format!(
+                    "Unknown theme {name:?}, expected one of default, {} or \
+                        a directory in {}",
+                    bundled.join(", "),
+                    args.themes_path.display()
+                ));
+            }
+
+            // Themes may hold only templates or only static files
+            let templates = directory.join("templates");
+            if is_directory && templates.is_dir() {
+                layers.templates.push(templates);
+            }
+            let static_dir = directory.join("static");
+            if is_directory && static_dir.is_dir() {
+                layers.static_dirs.push(static_dir);
+            }
+        }
+
+        layers.templates.extend(args.templates_path.clone());
+        layers.static_dirs.extend(args.static_path.clone());
+        Ok(layers)
+    }
+}
+
+/// Reads a static file from the highest layer that holds one by that name,
+/// which may be a path into subdirectories like `images/map.png`.
+///
+/// # Errors
+/// Fails if the name isn't a safe relative path, if a file can't be read
+/// or if there's no file by that name in any layer.
+pub fn read_static(
+    layers: &Layers,
+    name: &str,
+) -> io::Result<Cow<'static, [u8]>> {
+    if !is_safe(name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid static file name {name}"),
+        ));
+    }
+
+    if let Some(path) = find_file(layers, name) {
+        return fs::read(path).map(Cow::Owned);
+    }
+
+    layers
+        .theme
+        .map_or(&[][..], |theme| theme.static_files)
+        .iter()
+        .chain(&STATIC)
+        .find(|&&(known, _)| known == name)
+        .map(|&(_, content)| Cow::Borrowed(content))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No static file named {name}"),
+            )
+        })
+}
+
+/// When a static file was last modified, if it's read from a directory
+/// rather than built into en.
+pub fn modified(layers: &Layers, name: &str) -> Option<SystemTime> {
+    let path = find_file(layers, name)?;
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+// Finds a static file in the highest directory that holds one by that name
+fn find_file(layers: &Layers, name: &str) -> Option<PathBuf> {
+    if !is_safe(name) {
+        return None;
+    }
+
+    layers
+        .static_dirs
+        .iter()
+        .rev()
+        .map(|directory| directory.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Lists the names of every static file in any layer, including the ones
+/// built into en, sorted and without duplicates.
+///
+/// # Errors
+/// Fails if a directory can't be read.
+pub fn list_static(layers: &Layers) -> io::Result<Vec<String>> {
+    let mut names: Vec<String> = STATIC
+        .iter()
+        .chain(layers.theme.map_or(&[][..], |theme| theme.static_files))
+        .map(|&(name, _)| name.to_owned())
+        .collect();
+
+    for directory in &layers.static_dirs {
+        names.extend(
+            list_files(directory)?
+                .into_iter()
+                .map(|(_, name)| name)
+                .filter(|name| is_safe(name)),
+        );
+    }
+
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Lists the files under a directory with their names, which are their
+/// paths relative to it using `/` as separator.
+///
+/// # Errors
+/// Fails if a directory can't be read.
+pub fn list_files(directory: &Path) -> io::Result<Vec<(PathBuf, String)>> {
+    let mut files = vec![];
+    collect_files(directory, "", &mut files)?;
+    Ok(files)
+}
+
+fn collect_files(
+    directory: &Path,
+    prefix: &str,
+    files: &mut Vec<(PathBuf, String)>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let name = format!("{prefix}{file_name}");
+
+        if path.is_dir() {
+            collect_files(&path, &format!("{name}/"), files)?;
+        } else {
+            files.push((path, name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Guesses the content type of a file from its extension, falling back to
+/// `application/octet-stream`.
+pub fn guess_type(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "css" => "text/css",
+        "html" | "htm" => "text/html",
+        "js" | "mjs" => "text/javascript",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "wasm" => "application/wasm",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+// Static file names are relative paths that stay inside their directory and
+// don't reach hidden files, such as `.git`
+fn is_safe(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains('\\')
+        && name.split('/').all(|part| {
+            !part.is_empty() && !part.starts_with('.') && !part.contains(':')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(name: &str) -> Config {
+        Config {
+            theme: name.to_owned(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn embedded() {
+        let layers = Layers::default();
+        let style = read_static(&layers, "style.css").unwrap();
+        assert_eq!(style, fs::read("./static/style.css").unwrap());
+        assert!(read_static(&layers, "graph.toml").is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-static-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("style.css"), "body {}").unwrap();
+
+        let layers = Layers {
+            static_dirs: vec![directory.clone()],
+            ..Layers::default()
+        };
+        let style = read_static(&layers, "style.css").unwrap();
+        assert_eq!(style, b"body {}".as_slice());
+        let favicon = read_static(&layers, "favicon.svg").unwrap();
+        assert!(matches!(favicon, Cow::Borrowed(_)));
+        assert!(modified(&layers, "style.css").is_some());
+        assert!(modified(&layers, "favicon.svg").is_none());
+
+        fs::create_dir_all(directory.join("images")).unwrap();
+        fs::write(directory.join("images/map.png"), "png").unwrap();
+        fs::write(directory.join(".hidden"), "").unwrap();
+        assert_eq!(
+            read_static(&layers, "images/map.png").unwrap(),
+            b"png".as_slice()
+        );
+
+        // Served as not found rather than failing to read a directory
+        let error = read_static(&layers, "images").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(modified(&layers, "images").is_none());
+        assert_eq!(
+            list_static(&layers).unwrap(),
+            ["favicon.svg", "images/map.png", "style.css", "theme.css"]
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unsafe_names() {
+        let layers = Layers::default();
+        for name in [
+            "../Cargo.toml",
+            "/etc/passwd",
+            ".git/config",
+            "a//b",
+            "",
+            "C:x",
+        ] {
+            let error = read_static(&layers, name).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(guess_type("style.css"), "text/css");
+        assert_eq!(guess_type("images/Map.PNG"), "image/png");
+        assert_eq!(guess_type("paper.pdf"), "application/pdf");
+        assert_eq!(guess_type("LICENSE"), "application/octet-stream");
+    }
+
+    #[test]
+    fn bundled_themes() {
+        let args = Arguments::new();
+        let default = Layers::new(&args, &theme("default")).unwrap();
+        assert!(default.theme.is_none());
+
+        let serif = Layers::new(&args, &theme("serif")).unwrap();
+        let css = read_static(&serif, "theme.css").unwrap();
+        assert_eq!(css, fs::read("./themes/serif/static/theme.css").unwrap());
+        let style = read_static(&serif, "style.css").unwrap();
+        assert_eq!(style, fs::read("./static/style.css").unwrap());
+
+        let unknown = Layers::new(&args, &theme("Xq4jPz")).err().unwrap();
+        assert!(unknown.starts_with("Unknown theme \"Xq4jPz\""));
+        assert!(Layers::new(&args, &theme("../static")).is_err());
+    }
+
+    #[test]
+    fn theme_directory() {
+        let themes = std::env::temp_dir()
+            .join(format!("en-themes-{}", std::process::id()));
+        fs::create_dir_all(themes.join("night/static")).unwrap();
+        fs::write(themes.join("night/static/theme.css"), "a {}").unwrap();
+        fs::create_dir_all(themes.join("plain/templates")).unwrap();
+
+        let args = Arguments {
+            themes_path: themes.clone(),
+            static_path: Some(PathBuf::from("nonexistent-Hb7e")),
+            ..Arguments::new()
+        };
+        let night = Layers::new(&args, &theme("night")).unwrap();
+        assert_eq!(night.theme.unwrap().name, "night");
+        assert_eq!(
+            read_static(&night, "theme.css").unwrap(),
+            b"a {}".as_slice()
+        );
+        assert_eq!(
+            night.static_dirs.last().unwrap(),
+            Path::new("nonexistent-Hb7e")
+        );
+
+        let plain = Layers::new(&args, &theme("plain")).unwrap();
+        assert!(plain.theme.is_none());
+        assert_eq!(plain.static_dirs.len(), 1);
+        assert_eq!(
+            plain.templates.first().unwrap(),
+            &themes.join("plain/templates")
+        );
+
+        fs::remove_dir_all(&themes).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use tower::ServiceExt as _;
+
+use crate::{
+    prelude::*,
+    router::{
+        assets,
+        compression::{self, Effort, Encoding},
+    },
+    syntax::command::Arguments,
+    types::Graph,
+};
+
+/// Renders every page of the site through the router and writes it under
+/// `directory`, returning the number of files written. HTML pages are
+/// written with an `.html` extension, e.g. `/node/Syntax` becomes
+/// `node/Syntax.html`, and other files, including static files, keep their
+/// paths. With `compress`, text files also get `.gz` and `.br` siblings,
+/// for servers that can send those to clients that accept them.
+///
+/// # Errors
+/// Fails if a page can't be rendered or a file can't be written.
+pub async fn write(
+    graph: &Graph,
+    directory: &Path,
+    compress: bool,
+) -> io::Result<usize> {
+    let router = super::new(graph);
+    let mut count: usize = 0;
+
+    for path in list_paths(graph)? {
+        let request = Request::builder()
+            .uri(encode(&path))
+            .body(Body::empty())
+            .map_err(io::Error::other)?;
+        let response = router
+            .clone()
+            .oneshot(request)
+            .await
+            .map_err(io::Error::other)?;
+
+        if response.status() != StatusCode::OK {
+            return Err(io::Error::other(format!(
+                "Failed to render {path}: status {}",
+                response.status()
+            )));
+        }
+
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        let is_html = content_type.starts_with("text/html");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .map_err(io::Error::other)?;
+
+        let Some(file) = make_file_path(directory, &path, is_html) else {
+            log!("Skipped {path}, which can't be written as a file");
+            continue;
+        };
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, &body)?;
+        count = count.saturating_add(1);
+
+        if compress && compression::is_text(&content_type) {
+            count = count.saturating_add(write_compressed(&file, &body)?);
+        }
+    }
+
+    Ok(count)
+}
+
+// Writes a copy of `file` for each encoding, unless it's no smaller
+fn write_compressed(file: &Path, body: &[u8]) -> io::Result<usize> {
+    let mut count: usize = 0;
+
+    for encoding in [Encoding::Gzip, Encoding::Brotli] {
+        let compressed = compression::compress(encoding, body, Effort::Best)?;
+        if compressed.len() >= body.len() {
+            continue;
+        }
+
+        let mut name = file.as_os_str().to_owned();
+        name.push(".");
+        name.push(encoding.extension());
+        fs::write(PathBuf::from(name), compressed)?;
+        count = count.saturating_add(1);
+    }
+
+    Ok(count)
+}
+
+fn list_paths(graph: &Graph) -> io::Result<Vec<String>> {
+    let config = &graph.meta.config;
+    let mut paths = vec![String::from("/")];
+
+    let layers = assets::Layers::new(&Arguments::new().parse(), config)
+        .map_err(io::Error::other)?;
+    paths.extend(
+        assets::list_static(&layers)?
+            .iter()
+            .map(|name| format!("/static/{name}")),
+    );
+
+    if config.about {
+        paths.push(String::from("/about"));
+    }
+    if config.tree {
+        paths.push(String::from("/tree"));
+    }
+    if config.raw && config.raw_toml {
+        paths.push(String::from("/graph/toml"));
+    }
+    if config.raw && config.raw_json {
+        paths.push(String::from("/graph/json"));
+    }
+
+    let mut ids: Vec<&String> = graph.nodes.keys().collect();
+    ids.sort();
+    paths.extend(ids.into_iter().map(|id| format!("/node/{id}")));
+
+    Ok(paths)
+}
+
+// Node IDs become a single file name, so they can't hold separators or
+// point outside the directory
+fn make_file_path(
+    directory: &Path,
+    path: &str,
+    is_html: bool,
+) -> Option<PathBuf> {
+    let relative = if path == "/" {
+        String::from("index.html")
+    } else if is_html && !path.starts_with("/static/") {
+        format!("{}.html", path.trim_start_matches('/'))
+    } else {
+        path.trim_start_matches('/').to_owned()
+    };
+
+    let id = path.strip_prefix("/node/").unwrap_or_default();
+    let is_safe = !id.contains(['/', '\\'])
+        && Path::new(&relative)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+    is_safe.then(|| directory.join(relative))
+}
+
+/// Percent-encodes everything outside of unreserved characters and
+/// slashes, which makes any path valid in a URL or a header.
+pub(in crate::router) fn encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::serial::populate_graph;
+
+    use super::*;
+
+    #[test]
+    fn file_paths() {
+        let directory = Path::new("/tmp/site");
+        assert_eq!(
+            make_file_path(directory, "/", true).unwrap(),
+            directory.join("index.html")
+        );
+        assert_eq!(
+            make_file_path(directory, "/node/Syntax", true).unwrap(),
+            directory.join("node/Syntax.html")
+        );
+        assert_eq!(
+            make_file_path(directory, "/graph/json", false).unwrap(),
+            directory.join("graph/json")
+        );
+        assert_eq!(
+            make_file_path(directory, "/static/page.html", true).unwrap(),
+            directory.join("static/page.html")
+        );
+        assert!(make_file_path(directory, "/node/..", false).is_none());
+        assert!(make_file_path(directory, "/node/a/b", true).is_none());
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode("/node/Some Node?"), "/node/Some%20Node%3F");
+        assert_eq!(encode("/node/駄"), "/node/%E9%A7%84");
+    }
+
+    #[tokio::test]
+    async fn site() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-build-{}", std::process::id()));
+        let graph = populate_graph();
+
+        let count = write(&graph, &directory, false).await.unwrap();
+        assert_eq!(count, list_paths(&graph).unwrap().len());
+
+        let syntax =
+            fs::read_to_string(directory.join("node/Syntax.html")).unwrap();
+        assert!(syntax.contains("<h1 class=\"node-title\">Syntax</h1>"));
+        assert!(directory.join("static/style.css").is_file());
+        assert!(directory.join("index.html").is_file());
+
+        assert!(!directory.join("index.html.gz").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn compressed_site() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-build-compressed-{}", std::process::id()));
+        let graph = populate_graph();
+
+        let count = write(&graph, &directory, true).await.unwrap();
+        assert!(count > list_paths(&graph).unwrap().len());
+
+        let json = fs::read(directory.join("graph/json")).unwrap();
+        let gzip = fs::read(directory.join("graph/json.gz")).unwrap();
+        assert!(gzip.len() < json.len());
+        assert!(directory.join("graph/json.br").is_file());
+        assert!(directory.join("node/Syntax.html.br").is_file());
+        assert!(directory.join("static/style.css.gz").is_file());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    time::SystemTime,
+};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode},
+    middleware::Next,
+};
+
+use crate::{
+    prelude::*,
+    router::{compression, handlers},
+    syntax::{command::Arguments, serial::settings},
+    types::Config,
+};
+
+// What a client can send back to ask whether its copy is still current
+struct Validators {
+    etag: String,
+    modified: Option<SystemTime>,
+}
+
+/// Adds `ETag` and `Last-Modified` to pages and static files, answering
+/// requests whose `If-None-Match` or `If-Modified-Since` show the client
+/// already has the current version with `304 Not Modified`.
+///
+/// Pages are tagged before they're rendered, from the graph file, the
+/// settings, the templates and the request, so unchanged pages aren't
+/// rendered again. They get no `Last-Modified`, since they depend on more
+/// files than one date can stand for. Static files are tagged from their
+/// content as it's sent, so each encoding of a file gets its own tag.
+pub async fn validate(request: Request, next: Next) -> Response<Body> {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_owned();
+    let conditions = request.headers().clone();
+
+    if path.starts_with("/static/") {
+        let response = next.run(request).await;
+        return tag_content(response, &conditions).await;
+    }
+
+    if !is_page(&path) {
+        return next.run(request).await;
+    }
+
+    let Some(validators) = make_page_validators(&request) else {
+        return next.run(request).await;
+    };
+    if is_fresh(&conditions, &validators) {
+        // Nodes are negotiated by Accept unless they have a suffix
+        let vary = if path.starts_with("/node/") {
+            HeaderValue::from_static("accept, accept-encoding")
+        } else {
+            HeaderValue::from_static("accept-encoding")
+        };
+        return not_modified(&validators, Some(&vary));
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::OK {
+        insert_validators(response.headers_mut(), &validators);
+    }
+    response
+}
+
+// Pages rendered from the graph, as opposed to forms, search and the API
+fn is_page(path: &str) -> bool {
+    matches!(path, "/" | "/about" | "/tree")
+        || path.starts_with("/graph/")
+        || path.starts_with("/node/") && !path.ends_with("/edit")
+}
+
+fn make_page_validators(request: &Request) -> Option<Validators> {
+    let args = Arguments::new().parse();
+    let source = fs::read(&args.graph_path).ok()?;
+    let config = settings::layer(Config::default()).ok()?;
+    let templates = handlers::template::fingerprint().ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    source.hash(&mut hasher);
+    format!("{config:?}").hash(&mut hasher);
+    templates.hash(&mut hasher);
+    request.uri().hash(&mut hasher);
+    request
+        .headers()
+        .get(header::ACCEPT)
+        .map(HeaderValue::as_bytes)
+        .hash(&mut hasher);
+    compression::choose(request.headers()).hash(&mut hasher);
+
+    Some(Validators {
+        etag: format!("\"{:016x}\"", hasher.finish()),
+        modified: None,
+    })
+}
+
+// Static files are cheap to read, so they're tagged after reading them
+async fn tag_content(
+    response: Response<Body>,
+    conditions: &HeaderMap,
+) -> Response<Body> {
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log!("Failed to read response body: {e}");
+            return handlers::error::by_code(Some(500), None);
+        },
+    };
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    let validators = Validators {
+        etag: format!("\"{:016x}\"", hasher.finish()),
+        modified: parts
+            .headers
+            .get(header::LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|text| httpdate::parse_http_date(text).ok()),
+    };
+
+    if is_fresh(conditions, &validators) {
+        return not_modified(&validators, parts.headers.get(header::VARY));
+    }
+
+    insert_validators(&mut parts.headers, &validators);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+// `If-None-Match` takes precedence, and `If-Modified-Since` only counts
+// without it, as in RFC 9110
+fn is_fresh(conditions: &HeaderMap, validators: &Validators) -> bool {
+    if let Some(tags) = conditions.get(header::IF_NONE_MATCH) {
+        return tags.to_str().is_ok_and(|text| {
+            text.split(',').map(str::trim).any(|tag| {
+                tag == "*" || tag.trim_start_matches("W/") == validators.etag
+            })
+        });
+    }
+
+    let since = conditions
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|text| httpdate::parse_http_date(text).ok());
+
+    match (since, validators.modified) {
+        // HTTP dates have a precision of seconds
+        (Some(since_time), Some(modified)) => modified
+            .duration_since(since_time)
+            .map_or(true, |elapsed| elapsed.as_secs() == 0),
+        _ => false,
+    }
+}
+
+fn not_modified(
+    validators: &Validators,
+    vary: Option<&HeaderValue>,
+) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    insert_validators(response.headers_mut(), validators);
+    if let Some(value) = vary {
+        response.headers_mut().insert(header::VARY, value.clone());
+    }
+    response
+}
+
+fn insert_validators(headers: &mut HeaderMap, validators: &Validators) {
+    if let Ok(value) = HeaderValue::from_str(&validators.etag) {
+        headers.insert(header::ETAG, value);
+    }
+    if let Some(time) = validators.modified
+        && let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(time))
+    {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tower::ServiceExt as _;
+
+    use crate::syntax::serial::populate_graph;
+
+    use super::*;
+
+    async fn request(uri: &str, headers: &[(&str, &str)]) -> Response<Body> {
+        let mut builder = Request::builder().uri(uri);
+        for &(name, value) in headers {
+            builder = builder.header(name, value);
+        }
+
+        super::super::new(&populate_graph())
+            .oneshot(builder.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn etag(response: &Response<Body>) -> String {
+        response
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    }
+
+    #[tokio::test]
+    async fn pages() {
+        for uri in ["/", "/tree", "/node/Syntax", "/graph/toml"] {
+            let first = request(uri, &[]).await;
+            assert_eq!(first.status(), StatusCode::OK);
+            assert!(!first.headers().contains_key(header::LAST_MODIFIED));
+
+            let tag = etag(&first);
+            let second = request(uri, &[("if-none-match", &tag)]).await;
+            assert_eq!(second.status(), StatusCode::NOT_MODIFIED, "{uri}");
+            assert_eq!(etag(&second), tag);
+        }
+
+        let html = etag(&request("/node/Syntax", &[]).await);
+        let json =
+            request("/node/Syntax", &[("accept", "application/json")]).await;
+        assert_ne!(etag(&json), html);
+        let brotli =
+            request("/node/Syntax", &[("accept-encoding", "gzip, br")]).await;
+        assert_ne!(etag(&brotli), html);
+        let stale =
+            request("/node/Syntax", &[("if-none-match", "\"0\"")]).await;
+        assert_eq!(stale.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn static_files() {
+        let first = request("/static/style.css", &[]).await;
+        let tag = etag(&first);
+        let second =
+            request("/static/style.css", &[("if-none-match", &tag)]).await;
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+
+        let missing = request("/static/Jk2nQw.css", &[]).await;
+        assert!(!missing.headers().contains_key(header::ETAG));
+    }
+
+    #[tokio::test]
+    async fn not_cached() {
+        let api = request("/api/nodes", &[]).await;
+        assert!(!api.headers().contains_key(header::ETAG));
+    }
+
+    #[test]
+    fn modified_since() {
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let validators = Validators {
+            etag: String::from("\"a\""),
+            modified: Some(modified + Duration::from_millis(300)),
+        };
+        let since = |time: SystemTime| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::IF_MODIFIED_SINCE,
+                HeaderValue::from_str(&httpdate::fmt_http_date(time)).unwrap(),
+            );
+            headers
+        };
+
+        assert!(is_fresh(&since(modified), &validators));
+        assert!(!is_fresh(
+            &since(modified - Duration::from_secs(60)),
+            &validators
+        ));
+
+        let mut both = since(modified);
+        both.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"b\""));
+        assert!(!is_fresh(&both, &validators));
+    }
+}
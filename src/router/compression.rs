@@ -0,0 +1,353 @@
+use std::io::{self, Write as _};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, Response, StatusCode},
+    middleware::Next,
+};
+use flate2::{write::GzEncoder, Compression};
+
+use crate::prelude::*;
+
+/// A content coding en can compress responses with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+/// How much time to spend on making the output smaller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effort {
+    /// For responses, which are compressed on every request.
+    Fast,
+    /// For files written once, such as those of `en build`.
+    Best,
+}
+
+// Smaller bodies rarely get any smaller
+const MIN_SIZE: usize = 256;
+
+// Larger bodies are compressed off the async workers, as Brotli takes
+// around a millisecond for each 25 KB and would hold up other requests
+const BLOCKING_SIZE: usize = 16 * 1024;
+
+impl Encoding {
+    /// The name used in `Accept-Encoding` and `Content-Encoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// The extension of files holding content in this encoding.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gz",
+            Encoding::Brotli => "br",
+        }
+    }
+}
+
+/// Compresses responses whose content is text for clients that accept it,
+/// adding `Vary: accept-encoding` so caches keep one copy per encoding.
+pub async fn negotiate(request: Request, next: Next) -> Response<Body> {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+
+    let encoding = choose(request.headers());
+    let response = next.run(request).await;
+
+    let is_compressible = response.status() == StatusCode::OK
+        && !response.headers().contains_key(header::CONTENT_ENCODING)
+        && response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(is_text);
+    if !is_compressible {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+    let Some(chosen) = encoding else {
+        return Response::from_parts(parts, body);
+    };
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log!("Failed to read response body: {e}");
+            return super::handlers::error::by_code(Some(500), None);
+        },
+    };
+    if bytes.len() < MIN_SIZE {
+        return Response::from_parts(parts, Body::from(bytes));
+    }
+
+    let compressed = if bytes.len() < BLOCKING_SIZE {
+        compress(chosen, &bytes, Effort::Fast)
+    } else {
+        let input = bytes.clone();
+        tokio::task::spawn_blocking(move || {
+            compress(chosen, &input, Effort::Fast)
+        })
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))
+    };
+
+    match compressed {
+        Ok(compressed_bytes) => {
+            parts.headers.insert(
+                header::CONTENT_ENCODING,
+                HeaderValue::from_static(chosen.name()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(compressed_bytes))
+        },
+        Err(e) => {
+            log!("Failed to compress response with {}: {e}", chosen.name());
+            Response::from_parts(parts, Body::from(bytes))
+        },
+    }
+}
+
+/// Picks the encoding a client prefers from its `Accept-Encoding` header,
+/// favoring Brotli when it accepts both equally, or `None` if it accepts
+/// neither.
+pub fn choose(headers: &HeaderMap) -> Option<Encoding> {
+    let accepted = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())?;
+
+    // Quality values are kept in thousandths to avoid comparing floats
+    let mut wildcard: Option<u16> = None;
+    let mut brotli: Option<u16> = None;
+    let mut gzip: Option<u16> = None;
+    for item in accepted.split(',') {
+        let mut params = item.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default().to_ascii_lowercase();
+        let quality = params
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(Some(1000), parse_quality)
+            .unwrap_or(0);
+
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => wildcard = Some(quality),
+            _ => {},
+        }
+    }
+
+    let brotli_quality = brotli.or(wildcard).unwrap_or(0);
+    let gzip_quality = gzip.or(wildcard).unwrap_or(0);
+    if brotli_quality > 0 && brotli_quality >= gzip_quality {
+        Some(Encoding::Brotli)
+    } else if gzip_quality > 0 {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+// Reads a quality value such as `0.8` as 800, or `None` if it isn't one
+fn parse_quality(text: &str) -> Option<u16> {
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    match whole {
+        "1" if fraction.bytes().all(|b| b == b'0') => Some(1000),
+        "0" => format!("{fraction:0<3}").parse().ok(),
+        _ => None,
+    }
+}
+
+/// Whether content of this type is worth compressing, which is the case
+/// for text but not for images and archives that are compressed already.
+pub fn is_text(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    essence.starts_with("text/")
+        || matches!(
+            essence.as_str(),
+            "application/json" | "application/toml" | "image/svg+xml"
+        )
+}
+
+/// Compresses `bytes` with `encoding`.
+///
+/// # Errors
+/// Fails if the encoder fails, which writing to memory shouldn't cause.
+pub fn compress(
+    encoding: Encoding,
+    bytes: &[u8],
+    effort: Effort,
+) -> io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Gzip => {
+            let level = match effort {
+                Effort::Fast => Compression::fast(),
+                Effort::Best => Compression::best(),
+            };
+            let mut encoder = GzEncoder::new(vec![], level);
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        },
+        Encoding::Brotli => {
+            let params = brotli::enc::BrotliEncoderParams {
+                quality: match effort {
+                    Effort::Fast => 4,
+                    Effort::Best => 11,
+                },
+                ..brotli::enc::BrotliEncoderParams::default()
+            };
+            let mut input = bytes;
+            let mut output = vec![];
+            brotli::BrotliCompress(&mut input, &mut output, &params)?;
+            Ok(output)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use tower::ServiceExt as _;
+
+    use crate::syntax::serial::populate_graph;
+
+    use super::*;
+
+    fn accepting(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_str(value).unwrap(),
+        );
+        headers
+    }
+
+    fn decompress(encoding: Encoding, bytes: &[u8]) -> Vec<u8> {
+        let mut output = vec![];
+        match encoding {
+            Encoding::Gzip => flate2::read::GzDecoder::new(bytes)
+                .read_to_end(&mut output)
+                .unwrap(),
+            Encoding::Brotli => brotli::Decompressor::new(bytes, 4096)
+                .read_to_end(&mut output)
+                .unwrap(),
+        };
+        output
+    }
+
+    async fn get(uri: &str, encoding: &str) -> Response<Body> {
+        let request = Request::builder()
+            .uri(uri)
+            .header(header::ACCEPT_ENCODING, encoding)
+            .body(Body::empty())
+            .unwrap();
+        super::super::new(&populate_graph())
+            .oneshot(request)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiation() {
+        assert_eq!(choose(&HeaderMap::new()), None);
+        assert_eq!(choose(&accepting("gzip, deflate")), Some(Encoding::Gzip));
+        assert_eq!(choose(&accepting("gzip, br")), Some(Encoding::Brotli));
+        assert_eq!(choose(&accepting("br;q=0.5, gzip")), Some(Encoding::Gzip));
+        assert_eq!(choose(&accepting("*")), Some(Encoding::Brotli));
+        assert_eq!(choose(&accepting("*;q=0.2, br;q=0")), Some(Encoding::Gzip));
+        assert_eq!(choose(&accepting("identity, gzip;q=0")), None);
+        assert_eq!(choose(&accepting("gzip;q=2")), None);
+    }
+
+    #[test]
+    fn qualities() {
+        assert_eq!(parse_quality("1"), Some(1000));
+        assert_eq!(parse_quality("1.000"), Some(1000));
+        assert_eq!(parse_quality("0.8"), Some(800));
+        assert_eq!(parse_quality("0.25"), Some(250));
+        assert_eq!(parse_quality("0"), Some(0));
+        assert_eq!(parse_quality("1.5"), None);
+        assert_eq!(parse_quality("0.1234"), None);
+    }
+
+    #[test]
+    fn text_types() {
+        assert!(is_text("text/html"));
+        assert!(is_text("text/plain; charset=utf-8"));
+        assert!(is_text("application/json"));
+        assert!(is_text("image/svg+xml"));
+        assert!(!is_text("image/png"));
+        assert!(!is_text("application/gzip"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = "Some text, some text and some more text. ".repeat(20);
+        for encoding in [Encoding::Gzip, Encoding::Brotli] {
+            for effort in [Effort::Fast, Effort::Best] {
+                let compressed =
+                    compress(encoding, text.as_bytes(), effort).unwrap();
+                assert!(compressed.len() < text.len());
+                assert_eq!(decompress(encoding, &compressed), text.as_bytes());
+            }
+        }
+    }
+
+    // Compressed responses hold exactly what the uncompressed ones do
+    #[tokio::test]
+    async fn responses() {
+        for uri in ["/graph/json", "/graph/toml", "/tree", "/static/style.css"]
+        {
+            let plain = get(uri, "identity").await;
+            assert!(plain.headers().get(header::CONTENT_ENCODING).is_none());
+            assert!(
+                plain
+                    .headers()
+                    .get_all(header::VARY)
+                    .iter()
+                    .any(|value| value == "accept-encoding")
+            );
+            let plain_bytes =
+                axum::body::to_bytes(plain.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+
+            for encoding in [Encoding::Gzip, Encoding::Brotli] {
+                let response = get(uri, encoding.name()).await;
+                assert_eq!(
+                    response.headers().get(header::CONTENT_ENCODING).unwrap(),
+                    encoding.name()
+                );
+                let bytes =
+                    axum::body::to_bytes(response.into_body(), usize::MAX)
+                        .await
+                        .unwrap();
+                assert!(bytes.len() < plain_bytes.len(), "{uri}");
+                assert_eq!(decompress(encoding, &bytes), plain_bytes, "{uri}");
+            }
+        }
+
+        let missing = get("/node/Jk2nQwX", "gzip").await;
+        assert!(missing.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+}
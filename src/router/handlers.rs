@@ -1,4 +1,6 @@
+pub mod api;
 pub mod graph;
+pub mod edit;
 pub mod template;
 pub mod raw;
 pub mod navigation;
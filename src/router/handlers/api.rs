@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query},
+    http::{header, Response},
+};
+use serde_json::{json, Value};
+
+use crate::{
+    router::handlers,
+    syntax::{content, serial::populate_graph},
+    types::{Edge, Node},
+};
+
+const PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Lists nodes ordered by ID, optionally filtered with `hidden=true|false`
+/// and `tag=...`, paginated with `page` and `per_page`.
+pub async fn nodes(
+    Query(query): Query<HashMap<String, String>>,
+) -> Response<Body> {
+    let graph = populate_graph();
+
+    let hidden = match read_flag(&query, "hidden") {
+        Ok(hidden) => hidden,
+        Err(message) => return error(400, &message),
+    };
+    let tag = query.get("tag");
+
+    let mut nodes: Vec<&Node> = graph
+        .nodes
+        .values()
+        .filter(|node| hidden.is_none_or(|h| node.hidden == h))
+        .filter(|node| tag.is_none_or(|t| node.tags.contains(t)))
+        .collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let listed: Vec<Value> = nodes
+        .into_iter()
+        .map(|node| {
+            json!({
+                "id": node.id,
+                "title": node.title,
+                "summary": node.make_summary(),
+                "hidden": node.hidden,
+                "tags": node.tags,
+            })
+        })
+        .collect();
+
+    paginate("nodes", listed, &query)
+}
+
+/// A node with its text rendered to HTML and its outgoing and incoming
+/// edges.
+pub async fn node(Path(id): Path<String>) -> Response<Body> {
+    let graph = populate_graph();
+
+    let Some(node) = graph.find_node(&id) else {
+        return error(404, &format!("Could not find node ID {id}"));
+    };
+
+    let incoming = graph.incoming.get(&node.id).cloned().unwrap_or_default();
+
+    respond(
+        &json!({
+            "id": node.id,
+            "title": node.title,
+            "text": node.text,
+            "html": content::parse(&node.text),
+            "summary": node.make_summary(),
+            "hidden": node.hidden,
+            "tags": node.tags,
+            "links": node.links,
+            "outgoing": node.connections.clone().unwrap_or_default(),
+            "incoming": incoming,
+        }),
+        200,
+    )
+}
+
+/// Nodes connected to a node by edges in either direction, ordered by ID.
+/// Detached edges are left out since they lead to no node.
+pub async fn neighbors(Path(id): Path<String>) -> Response<Body> {
+    let graph = populate_graph();
+
+    let Some(node) = graph.find_node(&id) else {
+        return error(404, &format!("Could not find node ID {id}"));
+    };
+
+    let outgoing = node
+        .connections
+        .iter()
+        .flatten()
+        .filter(|edge| !edge.detached)
+        .map(|edge| (&edge.to, "outgoing"));
+    let incoming = graph
+        .incoming
+        .get(&node.id)
+        .into_iter()
+        .flatten()
+        .map(|edge| (&edge.from, "incoming"));
+
+    let mut found: BTreeMap<&String, Vec<&str>> = BTreeMap::new();
+    for (neighbor, direction) in outgoing.chain(incoming) {
+        let entry = found.entry(neighbor).or_default();
+        if !entry.contains(&direction) {
+            entry.push(direction);
+        }
+    }
+
+    let neighbors: Vec<Value> = found
+        .into_iter()
+        .filter_map(|(neighbor, directions)| {
+            graph.nodes.get(neighbor).map(|n| {
+                json!({
+                    "id": n.id,
+                    "title": n.title,
+                    "directions": directions,
+                })
+            })
+        })
+        .collect();
+
+    respond(&json!({ "id": node.id, "neighbors": neighbors }), 200)
+}
+
+/// Lists all edges ordered by origin and destination, optionally filtered
+/// with `from`, `to` and `kind`, paginated like nodes.
+pub async fn edges(
+    Query(query): Query<HashMap<String, String>>,
+) -> Response<Body> {
+    let graph = populate_graph();
+
+    let matches = |edge: &&Edge| {
+        ["from", "to", "kind"].into_iter().all(|key| {
+            query.get(key).is_none_or(|value| {
+                let field = match key {
+                    "from" => &edge.from,
+                    "to" => &edge.to,
+                    _ => &edge.kind,
+                };
+                field == value
+            })
+        })
+    };
+
+    let mut edges: Vec<&Edge> = graph
+        .nodes
+        .values()
+        .filter_map(|node| node.connections.as_ref())
+        .flatten()
+        .filter(matches)
+        .collect();
+    edges.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+
+    let listed: Vec<Value> =
+        edges.into_iter().map(|edge| json!(edge)).collect();
+
+    paginate("edges", listed, &query)
+}
+
+pub async fn not_found() -> Response<Body> {
+    error(404, "No such API endpoint")
+}
+
+fn paginate(
+    key: &str,
+    items: Vec<Value>,
+    query: &HashMap<String, String>,
+) -> Response<Body> {
+    let (page, per_page) = match (
+        read_number(query, "page", 1),
+        read_number(query, "per_page", PAGE_SIZE),
+    ) {
+        (Ok(page), Ok(per_page)) if per_page <= MAX_PAGE_SIZE => {
+            (page, per_page)
+        },
+        (Err(message), _) | (_, Err(message)) => return error(400, &message),
+        (_, Ok(_)) => {
+            return error(
+                400,
+                &format!("Parameter per_page can't be over {MAX_PAGE_SIZE}"),
+            );
+        },
+    };
+
+    let total = items.len();
+    let skipped = page.saturating_sub(1).saturating_mul(per_page);
+    let listed: Vec<Value> =
+        items.into_iter().skip(skipped).take(per_page).collect();
+
+    let mut body = serde_json::Map::new();
+    body.insert(key.to_owned(), Value::Array(listed));
+    body.insert(String::from("page"), json!(page));
+    body.insert(String::from("per_page"), json!(per_page));
+    body.insert(String::from("total"), json!(total));
+
+    respond(&Value::Object(body), 200)
+}
+
+fn read_number(
+    query: &HashMap<String, String>,
+    key: &str,
+    default: usize,
+) -> Result<usize, String> {
+    query.get(key).map_or(Ok(default), |raw| {
+        raw.parse()
+            .ok()
+            .filter(|&number| number > 0)
+            .ok_or_else(|| format!("Parameter {key} must be a positive number"))
+    })
+}
+
+fn read_flag(
+    query: &HashMap<String, String>,
+    key: &str,
+) -> Result<Option<bool>, String> {
+    query.get(key).map_or(Ok(None), |raw| {
+        raw.parse()
+            .map(Some)
+            .map_err(|_e| format!("Parameter {key} must be true or false"))
+    })
+}
+
+fn error(status: u16, message: &str) -> Response<Body> {
+    respond(
+        &json!({ "error": { "status": status, "message": message } }),
+        status,
+    )
+}
+
+fn respond(value: &Value, status: u16) -> Response<Body> {
+    handlers::raw::make_response(
+        &value.to_string(),
+        status,
+        &[(header::CONTENT_TYPE, "application/json")],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+
+    async fn read(response: Response<Body>) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> Query<HashMap<String, String>> {
+        Query(
+            pairs
+                .iter()
+                .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn list_nodes() {
+        let all = read(nodes(query(&[])).await).await;
+        let total = all.get("total").unwrap().as_u64().unwrap();
+        assert!(total > 2);
+
+        let page =
+            read(nodes(query(&[("per_page", "2"), ("page", "2")])).await).await;
+        assert_eq!(page.get("nodes").unwrap().as_array().unwrap().len(), 2);
+        assert_eq!(page.get("total").unwrap().as_u64().unwrap(), total);
+
+        let hidden = read(nodes(query(&[("hidden", "true")])).await).await;
+        let listed = hidden.get("nodes").unwrap().as_array().unwrap();
+        assert!(listed.iter().all(|n| n.get("hidden") == Some(&json!(true))));
+
+        let tagged = read(nodes(query(&[("tag", "syntax")])).await).await;
+        let ids: Vec<&Value> = tagged
+            .get("nodes")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|n| n.get("id").unwrap())
+            .collect();
+        assert_eq!(ids, [&json!("AnchorSyntax"), &json!("Syntax")]);
+    }
+
+    #[tokio::test]
+    async fn bad_parameters() {
+        for pairs in [
+            [("page", "0")],
+            [("per_page", "x")],
+            [("per_page", "501")],
+            [("hidden", "yes")],
+        ] {
+            let response = nodes(query(&pairs)).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = read(response).await;
+            assert_eq!(body.pointer("/error/status"), Some(&json!(400)));
+        }
+    }
+
+    #[tokio::test]
+    async fn single_node() {
+        let body = read(node(Path(String::from("toml"))).await).await;
+        assert_eq!(body.get("id"), Some(&json!("TOML")));
+        let html = body.get("html").unwrap().as_str().unwrap();
+        assert!(html.contains("<a href=\"https://toml.io\""));
+        assert!(!body.get("incoming").unwrap().as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_node() {
+        let response = node(Path(String::from("Qz8vLm2N"))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = read(response).await;
+        assert_eq!(
+            body.pointer("/error/message"),
+            Some(&json!("Could not find node ID Qz8vLm2N"))
+        );
+    }
+
+    #[tokio::test]
+    async fn node_neighbors() {
+        let body = read(neighbors(Path(String::from("TOML"))).await).await;
+        let listed = body.get("neighbors").unwrap().as_array().unwrap();
+        let en = listed
+            .iter()
+            .find(|n| n.get("id") == Some(&json!("en")))
+            .unwrap();
+        assert!(en.get("directions").unwrap().as_array().is_some());
+    }
+
+    #[tokio::test]
+    async fn filtered_edges() {
+        let body = read(edges(query(&[("from", "en")])).await).await;
+        let listed = body.get("edges").unwrap().as_array().unwrap();
+        assert!(!listed.is_empty());
+        assert!(listed.iter().all(|e| e.get("from") == Some(&json!("en"))));
+    }
+}
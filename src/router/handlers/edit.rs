@@ -0,0 +1,391 @@
+use std::io;
+
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{header, HeaderMap, Response},
+    response::{IntoResponse as _, Redirect},
+    Form,
+};
+
+use crate::{
+    prelude::*,
+    router::{build::encode, handlers},
+    syntax::{
+        command::Arguments,
+        serial::{
+            edit::{self, make_version, Mutation, NodeFields, Stale},
+            journal, populate_graph,
+        },
+    },
+    types::Node,
+};
+
+pub async fn form(Path(id): Path<String>) -> Response<Body> {
+    let graph = populate_graph();
+
+    if !graph.nodes.contains_key(&id)
+        && let Some(key) = graph.lowercase_keymap.get(&id)
+    {
+        return Redirect::permanent(&format!("/node/{}/edit", encode(key)))
+            .into_response();
+    }
+
+    if !is_valid_id(&id) {
+        return invalid_id(&id);
+    }
+
+    let existing = graph.nodes.get(&id).cloned();
+    let node = existing.clone().unwrap_or(Node {
+        id: id.clone(),
+        ..Node::default()
+    });
+
+    // Titles are filled in from IDs, so only show ones that differ
+    let title = if node.title == node.id {
+        ""
+    } else {
+        &node.title
+    };
+
+    let mut context = tera::Context::new();
+    context.insert("node", &node);
+    context.insert("title", title);
+    context.insert("is_new", &existing.is_none());
+    context.insert("version", &make_version(existing.as_ref()));
+    context.insert("config", &graph.meta.config.parse_text());
+
+    handlers::template::by_filename(
+        "edit.html",
+        &context,
+        500,
+        Some(format!("Failed to generate edit page for node ID {id}.")),
+        false,
+    )
+}
+
+pub async fn save(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Form(submission): Form<Submission>,
+) -> Response<Body> {
+    if !is_same_origin(&headers) {
+        return cross_origin();
+    }
+    if !is_valid_id(&id) {
+        return invalid_id(&id);
+    }
+
+    let title = submission.title.trim();
+    let fields = NodeFields {
+        text: submission.text.replace("\r\n", "\n"),
+        title: if title == id { "" } else { title }.to_owned(),
+        links: submission
+            .links
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+        hidden: submission.hidden.is_some(),
+    };
+
+    let path = Arguments::new().parse().graph_path;
+    let mutation = Mutation::UpdateNode {
+        id: id.clone(),
+        fields,
+    };
+
+    // Saving waits for the graph's lock, which may be held by another
+    // writer, and saving over changes made after the form was loaded would
+    // lose them
+    let saved_path = path.clone();
+    let version = submission.version;
+    let saved_id = id.clone();
+    let saved = tokio::task::spawn_blocking(move || {
+        edit::save_unchanged(&saved_path, &[mutation], &saved_id, &version)
+    })
+    .await
+    .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+    match saved {
+        Ok(()) => {
+            log!("Saved node {id} to {}", path.display());
+            // Encoded so that building the header can't fail after saving
+            Redirect::to(&format!("/node/{}", encode(&id))).into_response()
+        },
+        Err(e)
+            if e.get_ref()
+                .and_then(|inner| inner.downcast_ref::<Stale>())
+                .is_some() =>
+        {
+            handlers::error::by_code(
+                Some(409),
+                Some(&format!(
+                    "Node {id} was changed after this form was loaded. \
+                    Reload the edit page to see the changes and edit again."
+                )),
+            )
+        },
+        Err(e) => handlers::error::by_code(
+            Some(500),
+            Some(&format!("Failed to save node {id}: {e}")),
+        ),
+    }
+}
+
+// Recent writes, newest first, with the option to undo the latest
+pub async fn journal() -> Response<Body> {
+    let path = Arguments::new().parse().graph_path;
+    let entries = match journal::read(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            return handlers::error::by_code(
+                Some(500),
+                Some(&format!("Failed to read journal: {e}")),
+            );
+        },
+    };
+
+    let listed: Vec<serde_json::Value> = entries
+        .iter()
+        .rev()
+        .take(JOURNAL_LENGTH)
+        .map(|entry| {
+            serde_json::json!({
+                "time": entry.time,
+                "age": entry.make_age(),
+                "changes": entry.changes,
+            })
+        })
+        .collect();
+
+    let mut context = tera::Context::new();
+    context.insert("entries", &listed);
+    context.insert("config", &populate_graph().meta.config.parse_text());
+
+    handlers::template::by_filename(
+        "journal.html",
+        &context,
+        500,
+        Some(String::from("Failed to generate journal page.")),
+        false,
+    )
+}
+
+pub async fn undo(headers: HeaderMap) -> Response<Body> {
+    if !is_same_origin(&headers) {
+        return cross_origin();
+    }
+
+    let path = Arguments::new().parse().graph_path;
+
+    let undone = tokio::task::spawn_blocking(move || journal::undo(&path, 1))
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+    match undone {
+        Ok(entries) => {
+            for entry in entries {
+                log!("Undid {}", entry.changes.join(", "));
+            }
+            Redirect::to("/journal").into_response()
+        },
+        Err(e) => handlers::error::by_code(
+            Some(409),
+            Some(&format!("Failed to undo the last edit: {e}")),
+        ),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct Submission {
+    text: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    links: String,
+    hidden: Option<String>,
+    #[serde(default)]
+    version: String,
+}
+
+// Identifies the state of a node, or its absence, when a form is loaded
+
+// Browsers say where a request came from, so forms on other sites can't
+// change the graph through a visitor's browser. Requests from other clients
+// carry neither header and are let through.
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    let read = |name: header::HeaderName| {
+        let value = headers.get(name)?;
+        value.to_str().ok()
+    };
+
+    if let Some(site) = read(header::HeaderName::from_static("sec-fetch-site"))
+    {
+        return matches!(site, "same-origin" | "none");
+    }
+
+    match (read(header::ORIGIN), read(header::HOST)) {
+        (None, _) => true,
+        (Some(origin), Some(host)) => origin
+            .split_once("://")
+            .is_some_and(|(_, authority)| authority.eq_ignore_ascii_case(host)),
+        (Some(_), None) => false,
+    }
+}
+
+fn cross_origin() -> Response<Body> {
+    handlers::error::by_code(
+        Some(403),
+        Some("Edits can only be made from this site's own pages."),
+    )
+}
+
+// Anchor destinations end at whitespace and pipes delimit them, and
+// control characters can't be shown or put in a URL
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id
+            .contains(|c: char| c.is_whitespace() || c.is_control() || c == '|')
+}
+
+fn invalid_id(id: &str) -> Response<Body> {
+    handlers::error::by_code(
+        Some(400),
+        Some(&format!(
+            "{id:?} is not a valid node ID. \
+            IDs can't be empty or contain whitespace, control characters \
+            or pipes."
+        )),
+    )
+}
+
+const JOURNAL_LENGTH: usize = 20;
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::*;
+
+    async fn body(response: Response<Body>) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn existing_node() {
+        let response = form(Path(String::from("Syntax"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let html = body(response).await;
+        assert!(html.contains("<h1>Edit Syntax</h1>"));
+        assert!(html.contains("&#x2F;node&#x2F;syntax"));
+    }
+
+    #[tokio::test]
+    async fn new_node() {
+        let response = form(Path(String::from("Qz8vLm2N"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(body(response).await.contains("<h1>Create Qz8vLm2N</h1>"));
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let response = form(Path(String::from("syntax"))).await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    }
+
+    #[tokio::test]
+    async fn invalid() {
+        let page = form(Path(String::from("Two words"))).await;
+        assert_eq!(page.status(), StatusCode::BAD_REQUEST);
+
+        let submission = Form(Submission {
+            text: String::new(),
+            title: String::new(),
+            links: String::new(),
+            hidden: None,
+            version: String::new(),
+        });
+        let saved =
+            save(Path(String::from("a|b")), HeaderMap::new(), submission).await;
+        assert_eq!(saved.status(), StatusCode::BAD_REQUEST);
+
+        for id in ["Bell\u{7}", "Line\u{85}break", "Del\u{7f}"] {
+            assert!(!is_valid_id(id), "{id:?}");
+        }
+        assert!(is_valid_id("Café/Menu"));
+        assert_eq!(encode("Café/Menu"), "Caf%C3%A9/Menu");
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for &(name, value) in pairs {
+            map.insert(name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn origins() {
+        assert!(is_same_origin(&HeaderMap::new()));
+        assert!(is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "same-origin"
+        )])));
+        assert!(!is_same_origin(&headers(&[(
+            "sec-fetch-site",
+            "cross-site"
+        )])));
+        assert!(is_same_origin(&headers(&[
+            ("origin", "http://localhost:3000"),
+            ("host", "localhost:3000"),
+        ])));
+        assert!(!is_same_origin(&headers(&[
+            ("origin", "https://example.com"),
+            ("host", "localhost:3000"),
+        ])));
+        assert!(!is_same_origin(&headers(&[("origin", "null")])));
+    }
+
+    #[tokio::test]
+    async fn rejected_saves() {
+        let submission = || {
+            Form(Submission {
+                text: String::from("Overwritten"),
+                title: String::new(),
+                links: String::new(),
+                hidden: None,
+                version: String::from("0"),
+            })
+        };
+
+        let forged = save(
+            Path(String::from("Syntax")),
+            headers(&[("sec-fetch-site", "cross-site")]),
+            submission(),
+        )
+        .await;
+        assert_eq!(forged.status(), StatusCode::FORBIDDEN);
+
+        let stale =
+            save(Path(String::from("Syntax")), HeaderMap::new(), submission())
+                .await;
+        assert_eq!(stale.status(), StatusCode::CONFLICT);
+
+        let undone = undo(headers(&[("sec-fetch-site", "same-site")])).await;
+        assert_eq!(undone.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn form_version() {
+        let html = body(form(Path(String::from("Syntax"))).await).await;
+        let graph = populate_graph();
+        let version = make_version(graph.nodes.get("Syntax"));
+        assert!(
+            html.contains(&format!("name=\"version\" value=\"{version}\""))
+        );
+        assert_ne!(version, make_version(None));
+    }
+}
@@ -5,7 +5,7 @@ use axum::{
 
 use crate::{syntax::serial::populate_graph, router::handlers};
 
-pub(in crate::router::handlers) fn by_code(
+pub(in crate::router) fn by_code(
     code: Option<u16>,
     message: Option<&str>,
 ) -> Response<Body> {
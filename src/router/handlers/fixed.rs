@@ -1,41 +1,96 @@
+use std::{collections::HashMap, io};
+
 use axum::{
     body::Body,
+    extract::{Path, Query},
     http::{Response, StatusCode, header, HeaderValue},
 };
 
 use crate::prelude::*;
 use crate::{
-    router::handlers,
-    syntax::serial::{Format, populate_graph, serialize_graph},
+    router::{assets, handlers},
+    syntax::{
+        command::Arguments,
+        serial::{
+            Format, modulate_graph, populate_graph, read_source,
+            serialize_graph,
+        },
+    },
 };
 
-/// # Panics
-/// Will panic if file read fails.
-#[expect(clippy::unused_async)]
-pub async fn file(file_path: &str, content_type: &str) -> Response<Body> {
-    let content = match std::fs::read(file_path) {
-        Ok(s) => s,
+/// Serves a file under `/static`, from the directory given with `--static`
+/// or the theme if either holds one, or else from the files built into en.
+/// The content type is guessed from the file's extension, and files read
+/// from a directory carry their modification time as `Last-Modified`.
+pub async fn file(Path(name): Path<String>) -> Response<Body> {
+    let args = Arguments::new().parse();
+    let layers = match assets::Layers::new(&args, &populate_graph().meta.config)
+    {
+        Ok(layers) => layers,
+        Err(e) => {
+            log!("Failed to find static files: {e}");
+            return handlers::error::by_code(Some(500), Some(&e));
+        },
+    };
+
+    let content = match assets::read_static(&layers, &name) {
+        Ok(content) => content,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::InvalidInput
+            ) =>
+        {
+            return handlers::error::by_code(
+                Some(404),
+                Some("The file you tried to access could not be found."),
+            );
+        },
         Err(e) => {
-            panic!("Failed to read {file_path} contents: {e}")
+            log!("Failed to read static file {name}: {e}");
+            return handlers::error::by_code(
+                Some(500),
+                Some(&format!("Failed to read static file {name}.")),
+            );
         },
     };
 
     let mut response = Response::new(Body::from(content));
     *response.status_mut() = StatusCode::OK;
-    let header = header::CONTENT_TYPE;
-
-    if let Ok(header_value) = HeaderValue::from_str(content_type) {
-        response.headers_mut().append(header, header_value);
-    } else {
-        log!("Failed to create content type header value from {content_type}");
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(assets::guess_type(&name)),
+    );
+    if let Some(time) = assets::modified(&layers, &name)
+        && let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(time))
+    {
+        response.headers_mut().insert(header::LAST_MODIFIED, value);
     }
 
     response
 }
 
+/// Serves the whole graph. By default, or with `view=source`, only what the
+/// author wrote is included, so the output can be read back as a graph.
+/// `view=expanded` adds the derived titles, edges, incoming edges and
+/// lowercase key map.
 #[expect(clippy::unused_async)]
-pub async fn serial(format: &Format) -> Response<Body> {
-    let graph = populate_graph();
+pub async fn serial(
+    format: &Format,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response<Body> {
+    let graph = match query.get("view").map(String::as_str) {
+        None | Some("source") => read_source(),
+        Some("expanded") => modulate_graph(read_source()),
+        Some(view) => {
+            return handlers::error::by_code(
+                Some(400),
+                Some(&format!(
+                    "Unknown view {view:?}, expected \"source\" or \"expanded\"."
+                )),
+            );
+        },
+    };
     let body = serialize_graph(format, &graph);
 
     match *format {
@@ -54,17 +109,19 @@ pub async fn serial(format: &Format) -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
+    use crate::syntax::serial::deserialize_graph;
+
     use super::*;
 
     #[tokio::test]
     async fn serial_toml() {
-        let response = serial(&Format::TOML).await;
+        let response = serial(&Format::TOML, Query(HashMap::new())).await;
         assert!(response.status() == 200);
     }
 
     #[tokio::test]
     async fn serial_toml_content_type() {
-        let response = serial(&Format::TOML).await;
+        let response = serial(&Format::TOML, Query(HashMap::new())).await;
         assert!(
             response.headers().get(header::CONTENT_TYPE).unwrap()
                 == "text/plain"
@@ -73,36 +130,66 @@ mod tests {
 
     #[tokio::test]
     async fn serial_json_content_type() {
-        let response = serial(&Format::JSON).await;
+        let response = serial(&Format::JSON, Query(HashMap::new())).await;
         assert!(
             response.headers().get(header::CONTENT_TYPE).unwrap()
                 == "application/json"
         );
     }
 
+    fn view(name: &str) -> Query<HashMap<String, String>> {
+        Query(HashMap::from([(String::from("view"), name.to_owned())]))
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
     #[tokio::test]
-    async fn file_valid_header() {
-        let payload = "y1mgMhjeIMFsRNZ1tskP52DfWuvhvbRP";
-        let response = file("./static/graph.toml", payload).await;
-        assert_eq!(
-            response.headers().get(header::CONTENT_TYPE).unwrap(),
-            payload
-        );
+    async fn serial_views() {
+        let source = body(serial(&Format::TOML, view("source")).await).await;
+        assert_eq!(deserialize_graph(&Format::TOML, &source), read_source());
+        assert!(!source.contains("[incoming"));
+
+        let expanded =
+            body(serial(&Format::JSON, view("expanded")).await).await;
+        assert!(expanded.contains(r#""incoming":{"#));
+        assert!(expanded.contains(r#""lowercase_keymap":{"#));
+
+        let unknown = serial(&Format::JSON, view("derived")).await;
+        assert_eq!(unknown.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn static_path(name: &str) -> Path<String> {
+        Path(name.to_owned())
     }
 
     #[tokio::test]
-    async fn file_invalid_header() {
-        let response = file("./static/graph.toml", "\n").await;
-        println!("{response:#?}");
-        assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+    async fn file_content_types() {
+        let style = file(static_path("style.css")).await;
+        assert_eq!(style.status(), StatusCode::OK);
+        assert_eq!(
+            style.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/css"
+        );
+
+        let favicon = file(static_path("favicon.svg")).await;
+        assert_eq!(
+            favicon.headers().get(header::CONTENT_TYPE).unwrap(),
+            "image/svg+xml"
+        );
     }
 
     #[tokio::test]
-    #[should_panic(
-        expected = "Failed to read IvnhZhdHb1xDnUw4hYDDNIERoaOojkiu \
-        contents: No such file or directory (os error 2)"
-    )]
-    async fn file_invalid_path() {
-        drop(file("IvnhZhdHb1xDnUw4hYDDNIERoaOojkiu", "text/plain").await);
+    async fn file_not_found() {
+        let missing =
+            file(static_path("IvnhZhdHb1xDnUw4hYDDNIERoaOojkiu")).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+        let traversal = file(static_path("../Cargo.toml")).await;
+        assert_eq!(traversal.status(), StatusCode::NOT_FOUND);
     }
 }
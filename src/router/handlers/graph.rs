@@ -1,32 +1,102 @@
 use axum::response::IntoResponse as _;
-use axum::{body::Body, extract::Path, http::Response, response::Redirect};
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, Response},
+    response::Redirect,
+};
 
 use crate::syntax::content;
 
-use crate::{syntax::serial::populate_graph, router::handlers, types::Node};
+use crate::{
+    router::handlers,
+    syntax::serial::{populate_graph, serialize_node, Format},
+    types::{Graph, Node},
+};
 
-pub async fn node(Path(id): Path<String>) -> Response<Body> {
+/// Serves a node as HTML, JSON, TOML or plain text, chosen by a `.json`,
+/// `.toml` or `.txt` suffix on the ID or else by the `Accept` header.
+pub async fn node(
+    Path(requested): Path<String>,
+    headers: HeaderMap,
+) -> Response<Body> {
     let graph = populate_graph();
-    let empty_node = Node::new(Some(format!("Could not find node ID {id}.")));
-    let node = graph.find_node(&id).unwrap_or(empty_node.clone());
+
+    // IDs may contain dots, so only treat a suffix as one when the whole ID
+    // doesn't name a node
+    let (id, suffix) = match Representation::from_suffix(&requested) {
+        Some((stem, representation))
+            if graph.find_node(&requested).is_none() =>
+        {
+            (stem.to_owned(), Some(representation))
+        },
+        _ => (requested, None),
+    };
 
     if !graph.nodes.contains_key(&id)
-        && graph.lowercase_keymap.contains_key(&id)
+        && let Some(key) = graph.lowercase_keymap.get(&id)
     {
-        return Redirect::permanent(format!("/node/{}", node.id).as_str())
+        let extension = suffix.map_or("", |r| r.extension());
+        return Redirect::permanent(&format!("/node/{key}{extension}"))
             .into_response();
     }
 
+    let representation = suffix.unwrap_or_else(|| {
+        Representation::from_accept(
+            headers
+                .get(header::ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default(),
+        )
+    });
+
+    let mut response = if representation == Representation::Html {
+        page(&graph, &id)
+    } else {
+        serialize(&graph, &id, representation)
+    };
+
+    // Without a suffix, the same URL answers differently per Accept header
+    if suffix.is_none() {
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("accept"));
+    }
+
+    response
+}
+
+fn page(graph: &Graph, id: &str) -> Response<Body> {
+    let empty_node = Node::new(Some(format!("Could not find node ID {id}.")));
+    let node = graph.find_node(id).unwrap_or(empty_node.clone());
+
     let mut context = tera::Context::new();
+    context.insert("id", &id);
     context.insert("node", &node);
     context.insert("text", &content::parse(&node.text));
-    context.insert("incoming", &graph.incoming.get(&id));
-    context.insert("config", &graph.meta.config.parse_text());
+    context.insert("summary", &node.make_summary());
+    context.insert("incoming", &graph.incoming.get(id));
+    context.insert("config", &graph.meta.config.clone().parse_text());
 
     let not_found = node == empty_node;
 
+    // Nodes may choose their own template, e.g. for essays or glossaries
+    let template = if node.template.is_empty() {
+        "node.html"
+    } else {
+        node.template.as_str()
+    };
+    if let Ok(false) = handlers::template::exists(template) {
+        return handlers::error::by_code(
+            Some(500),
+            Some(&format!(
+                "Node {id} uses template {template}, which does not exist."
+            )),
+        );
+    }
+
     handlers::template::by_filename(
-        "node.html",
+        template,
         &context,
         if not_found { 404 } else { 500 },
         Some(
@@ -41,8 +111,127 @@ pub async fn node(Path(id): Path<String>) -> Response<Body> {
     )
 }
 
+fn serialize(
+    graph: &Graph,
+    id: &str,
+    representation: Representation,
+) -> Response<Body> {
+    let content_type = representation.content_type();
+
+    let Some(node) = graph.nodes.get(id) else {
+        let message = format!("Could not find node ID {id}");
+        return if representation == Representation::Json {
+            handlers::raw::make_response(
+                &serde_json::json!({
+                    "error": { "status": 404, "message": message }
+                })
+                .to_string(),
+                404,
+                &[(header::CONTENT_TYPE, content_type)],
+            )
+        } else {
+            handlers::raw::make_response(
+                &message,
+                404,
+                &[(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            )
+        };
+    };
+
+    let incoming = graph.incoming.get(id).cloned().unwrap_or_default();
+    let body = match representation {
+        Representation::Json => serialize_node(&Format::JSON, node, &incoming),
+        Representation::Toml => serialize_node(&Format::TOML, node, &incoming),
+        Representation::Text | Representation::Html => {
+            format!("{}\n\n{}\n", node.title, content::plain(&node.text))
+        },
+    };
+
+    handlers::raw::make_response(
+        &body,
+        200,
+        &[(header::CONTENT_TYPE, content_type)],
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Representation {
+    Html,
+    Json,
+    Toml,
+    Text,
+}
+
+impl Representation {
+    const SUFFIXED: [Representation; 3] = [
+        Representation::Json,
+        Representation::Toml,
+        Representation::Text,
+    ];
+
+    fn from_suffix(id: &str) -> Option<(&str, Representation)> {
+        Self::SUFFIXED.into_iter().find_map(|representation| {
+            id.strip_suffix(representation.extension())
+                .filter(|stem| !stem.is_empty())
+                .map(|stem| (stem, representation))
+        })
+    }
+
+    // Picks the supported media type with the highest quality, preferring
+    // earlier ones on ties and HTML for wildcards or a missing header
+    fn from_accept(accept: &str) -> Representation {
+        let mut best = (Representation::Html, 0.0_f32);
+
+        for range in accept.split(',') {
+            let mut parameters = range.split(';').map(str::trim);
+            let media_type = parameters.next().unwrap_or_default();
+            let quality = parameters
+                .find_map(|parameter| parameter.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let representation = match media_type.to_ascii_lowercase().as_str()
+            {
+                "text/html" | "application/xhtml+xml" | "text/*" | "*/*" => {
+                    Representation::Html
+                },
+                "application/json" => Representation::Json,
+                "application/toml" => Representation::Toml,
+                "text/plain" => Representation::Text,
+                _ => continue,
+            };
+
+            if quality > best.1 {
+                best = (representation, quality);
+            }
+        }
+
+        best.0
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Representation::Html => "",
+            Representation::Json => ".json",
+            Representation::Toml => ".toml",
+            Representation::Text => ".txt",
+        }
+    }
+
+    const fn content_type(self) -> &'static str {
+        match self {
+            Representation::Html => "text/html",
+            Representation::Json => "application/json",
+            Representation::Toml => "application/toml",
+            Representation::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use axum::{
         http::{HeaderName, StatusCode},
     };
@@ -51,13 +240,13 @@ mod tests {
 
     #[tokio::test]
     async fn syntax() {
-        let response = node(Path("Syntax".to_string())).await;
+        let response = node(Path("Syntax".to_string()), HeaderMap::new()).await;
         assert_eq!(response.status(), StatusCode::OK);
     }
 
     #[tokio::test]
     async fn syntax_content_type() {
-        let response = node(Path("Syntax".to_string())).await;
+        let response = node(Path("Syntax".to_string()), HeaderMap::new()).await;
         assert!(
             response
                 .headers()
@@ -69,15 +258,141 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn description_meta() {
+        let response = node(Path("Graph".to_string()), HeaderMap::new()).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"<meta name="description" content="A graph"#));
+        assert!(html.contains(r#"<meta property="og:title" content="Graph">"#));
+    }
+
     #[tokio::test]
     async fn not_found() {
-        let response = node(Path("InexistentNode".to_string())).await;
+        let response =
+            node(Path("InexistentNode".to_string()), HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn body(response: Response<Body>) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn node_templates() {
+        let default = populate_graph();
+        let syntax = default.nodes.get("Syntax").unwrap().clone();
+        let with_node = |node: Node| Graph {
+            nodes: HashMap::from([(String::from("Syntax"), node)]),
+            ..default.clone()
+        };
+
+        let essay = with_node(Node {
+            template: String::from("empty.html"),
+            ..syntax.clone()
+        });
+        let rendered = page(&essay, "Syntax");
+        assert_eq!(rendered.status(), StatusCode::OK);
+        assert!(body(rendered).await.starts_with("<p>There are no nodes."));
+
+        let classed = with_node(Node {
+            classes: vec![String::from("essay"), String::from("wide")],
+            ..syntax.clone()
+        });
+        let html = body(page(&classed, "Syntax")).await;
+        assert!(html.contains(r#"<section class="node essay wide">"#));
+
+        let missing = with_node(Node {
+            template: String::from("Pq3vWz.html"),
+            ..syntax
+        });
+        let response = page(&missing, "Syntax");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body(response).await.contains("Pq3vWz.html"));
+    }
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    fn content_type(response: &Response<Body>) -> &str {
+        response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn accept_json() {
+        let response = node(
+            Path("TOML".to_string()),
+            accept("text/html;q=0.5, application/json"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(content_type(&response), "application/json");
+        assert_eq!(response.headers().get(header::VARY).unwrap(), "accept");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(json.pointer("/id").unwrap(), "TOML");
+        assert!(json.pointer("/incoming").unwrap().is_array());
+    }
+
+    #[tokio::test]
+    async fn browser_accept() {
+        let response = node(
+            Path("TOML".to_string()),
+            accept("text/html,application/xhtml+xml,*/*;q=0.8"),
+        )
+        .await;
+        assert_eq!(content_type(&response), "text/html");
+    }
+
+    #[tokio::test]
+    async fn suffixes() {
+        let toml =
+            node(Path("Syntax.toml".to_string()), accept("text/html")).await;
+        assert_eq!(content_type(&toml), "application/toml");
+        assert!(toml.headers().get(header::VARY).is_none());
+        let table: toml::Table = toml::from_str(&body(toml).await).unwrap();
+        assert_eq!(table.get("id").unwrap().as_str(), Some("Syntax"));
+
+        let text = node(Path("Syntax.txt".to_string()), HeaderMap::new()).await;
+        assert_eq!(content_type(&text), "text/plain; charset=utf-8");
+        assert!(body(text).await.starts_with("Syntax\n\n"));
+
+        let redirect =
+            node(Path("syntax.json".to_string()), HeaderMap::new()).await;
+        assert_eq!(
+            redirect.headers().get(header::LOCATION).unwrap(),
+            "/node/Syntax.json"
+        );
+    }
+
+    #[tokio::test]
+    async fn json_not_found() {
+        let response =
+            node(Path("InexistentNode.json".to_string()), HeaderMap::new())
+                .await;
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let json: serde_json::Value =
+            serde_json::from_str(&body(response).await).unwrap();
+        assert_eq!(json.pointer("/error/status").unwrap(), 404);
     }
 
     #[tokio::test]
     async fn redirect() {
-        let response = node(Path("syntax".to_string())).await;
+        let response = node(Path("syntax".to_string()), HeaderMap::new()).await;
         assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
     }
 }
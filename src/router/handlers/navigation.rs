@@ -12,7 +12,8 @@ pub async fn page(template: &str) -> Response<Body> {
     let mut context = tera::Context::new();
     let graph = populate_graph();
     let root_node = graph.get_root().unwrap_or_default();
-    let nodes: Vec<Node> = graph.nodes.into_values().collect();
+    let mut nodes: Vec<Node> = graph.nodes.into_values().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
 
     context.insert("nodes", &nodes);
     context.insert("root_node", &root_node);
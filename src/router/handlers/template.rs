@@ -3,7 +3,165 @@ use axum::{
     http::{header, Response, StatusCode},
 };
 
-use crate::{prelude::*, router::handlers::raw::make_response};
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    panic,
+    path::PathBuf,
+    sync::{PoisonError, RwLock},
+    time::SystemTime,
+};
+
+use crate::{
+    prelude::*,
+    router::{
+        assets::{self, Layers},
+        handlers::raw::make_response,
+    },
+    syntax::{command::Arguments, content, serial::populate_graph},
+};
+
+// Templates compiled once and shared by every render
+struct Cache {
+    tera: tera::Tera,
+    fingerprint: u64,
+    layers: Layers,
+    reload: bool,
+    stamps: Vec<Option<Stamp>>,
+}
+
+// The number of template files in a directory and when the latest of them
+// was modified, which changes when any of them is added, removed or saved
+type Stamp = (usize, SystemTime);
+
+// Where templates are kept, which tests can swap for their own so they
+// don't change the templates other tests render with
+type Shared = RwLock<Option<Cache>>;
+
+static CACHE: Shared = RwLock::new(None);
+
+/// Compiles the templates and keeps them for every render after it. With
+/// `reload`, templates in the directories of `layers` are compiled again
+/// whenever they change.
+///
+/// # Errors
+/// Fails if a template can't be read or parsed, keeping the templates
+/// loaded before.
+pub(in crate::router) fn init(
+    layers: &Layers,
+    reload: bool,
+) -> tera::Result<()> {
+    fill(&CACHE, layers, reload)
+}
+
+fn fill(cache: &Shared, layers: &Layers, reload: bool) -> tera::Result<()> {
+    let loaded = Cache {
+        tera: load(layers)?,
+        fingerprint: make_fingerprint(layers),
+        layers: layers.clone(),
+        reload,
+        stamps: make_stamps(&layers.templates),
+    };
+    *cache.write().unwrap_or_else(PoisonError::into_inner) = Some(loaded);
+    Ok(())
+}
+
+/// A hash of the source of every loaded template, which changes whenever
+/// pages rendered from them may change.
+///
+/// # Errors
+/// Fails if the templates can't be loaded.
+pub(in crate::router) fn fingerprint() -> tera::Result<u64> {
+    refresh(&CACHE)?;
+    Ok(CACHE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map_or(0, |loaded| loaded.fingerprint))
+}
+
+/// Describes a template error along with the errors that caused it, which
+/// hold the details, such as where parsing failed.
+pub(in crate::router) fn describe(error: &tera::Error) -> String {
+    let mut causes = vec![error.to_string()];
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    causes.join(": ")
+}
+
+// Loads the templates if they weren't yet, or again if reloading is on and
+// they changed
+fn refresh(shared: &Shared) -> tera::Result<()> {
+    let cache = shared.read().unwrap_or_else(PoisonError::into_inner);
+    let Some(ref loaded) = *cache else {
+        drop(cache);
+        let args = Arguments::new().parse();
+        let layers = Layers::new(&args, &populate_graph().meta.config)
+            .map_err(tera::Error::msg)?;
+        return fill(shared, &layers, args.dev);
+    };
+
+    if !loaded.reload || make_stamps(&loaded.layers.templates) == loaded.stamps
+    {
+        return Ok(());
+    }
+
+    let layers = loaded.layers.clone();
+    drop(cache);
+    fill(shared, &layers, true)?;
+    log!("Reloaded templates");
+    Ok(())
+}
+
+fn make_fingerprint(layers: &Layers) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    assets::TEMPLATES.hash(&mut hasher);
+    layers.theme.map(|theme| theme.templates).hash(&mut hasher);
+
+    for directory in &layers.templates {
+        let mut files = assets::list_files(directory).unwrap_or_default();
+        files.sort();
+        for (path, name) in files {
+            name.hash(&mut hasher);
+            fs::read(path).unwrap_or_default().hash(&mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
+
+fn make_stamps(directories: &[PathBuf]) -> Vec<Option<Stamp>> {
+    directories
+        .iter()
+        .map(|directory| {
+            let files = assets::list_files(directory).ok()?;
+            let latest = files
+                .iter()
+                .filter_map(|file| fs::metadata(&file.0).ok()?.modified().ok())
+                .max()
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+
+            Some((files.len(), latest))
+        })
+        .collect()
+}
+
+/// Whether a template by that name is loaded.
+///
+/// # Errors
+/// Fails if the templates can't be loaded.
+pub(in crate::router::handlers) fn exists(name: &str) -> tera::Result<bool> {
+    refresh(&CACHE)?;
+    Ok(CACHE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .is_some_and(|loaded| loaded.tera.get_template(name).is_ok()))
+}
 
 pub(in crate::router::handlers) fn by_filename(
     name: &str,
@@ -25,14 +183,28 @@ pub(in crate::router::handlers) fn render(
     // then template_handler can replace static_template_handler
     context: &tera::Context,
     error_message: Option<String>,
+) -> (String, u16) {
+    render_from(&CACHE, name, context, error_message)
+}
+
+fn render_from(
+    shared: &Shared,
+    name: &str,
+    context: &tera::Context,
+    error_message: Option<String>,
 ) -> (String, u16) {
     // TODO just return an Option/String> here
-    let tera = match tera::Tera::new("./templates/**/*") {
-        Ok(t) => t,
-        Err(e) => {
-            return (emergency_wrap(&e), 500);
-        },
+    if let Err(e) = refresh(shared) {
+        return (emergency_wrap(&e), 500);
+    }
+    let cache = shared.read().unwrap_or_else(PoisonError::into_inner);
+    let Some(ref loaded) = *cache else {
+        return (
+            emergency_wrap(&tera::Error::msg("No templates loaded")),
+            500,
+        );
     };
+    let tera = &loaded.tera;
 
     match tera.render(name, context) {
         Ok(t) => (t, 200),
@@ -68,6 +240,57 @@ pub(in crate::router::handlers) fn render(
     }
 }
 
+/// Compiles the templates built into en, replacing any of them with
+/// templates of the same name from each layer above them in turn.
+/// Templates with other names are added alongside them.
+///
+/// # Errors
+/// Fails if a template can't be read or parsed.
+pub(in crate::router) fn load(layers: &Layers) -> tera::Result<tera::Tera> {
+    let mut tera = tera::Tera::default();
+    tera.add_raw_templates(assets::TEMPLATES)?;
+    if let Some(theme) = layers.theme {
+        tera.add_raw_templates(theme.templates.iter().copied())?;
+    }
+
+    for dir in &layers.templates {
+        let files = assets::list_files(dir).map_err(|e| {
+            tera::Error::msg(format!(
+                "Failed to read templates in {}: {e}",
+                dir.display()
+            ))
+        })?;
+        let named: Vec<(PathBuf, Option<String>)> = files
+            .into_iter()
+            .map(|(path, name)| (path, Some(name)))
+            .collect();
+        tera.add_template_files(named)?;
+    }
+
+    tera.register_filter("plain", plain_filter);
+    Ok(tera)
+}
+
+// Renders en syntax to plain text, e.g. `{{ node.text | plain }}`. The
+// lexer panics on text ending inside a header or preformatted block, and
+// since one such node would break every page listing it, its raw text is
+// shown instead.
+fn plain_filter(
+    value: &tera::Value,
+    _arguments: &HashMap<String, tera::Value>,
+) -> tera::Result<tera::Value> {
+    value
+        .as_str()
+        .map(|text| {
+            let plain = panic::catch_unwind(|| content::plain(text))
+                .unwrap_or_else(|_| text.to_owned());
+            tera::Value::String(plain)
+        })
+        .ok_or_else(|| {
+            tera::Error::msg(format!("Filter plain expects a string: {value}"))
+        })
+}
+
 fn emergency_wrap(error: &tera::Error) -> String {
     log!("{error:#?}");
     format!(
@@ -153,6 +376,26 @@ mod tests {
         assert!(body.matches(payload).count() == 1);
     }
 
+    #[test]
+    fn render_tree_plain_text() {
+        let graph = crate::syntax::serial::populate_graph();
+        let node = crate::types::Node::new(Some(String::from(
+            "A |bbP6yFXi2m|Somewhere node with `R0pCKXcsvQ`.",
+        )));
+        let config = crate::types::Config {
+            tree_node_text: true,
+            ..graph.meta.config
+        };
+        let mut context = tera::Context::new();
+        context.insert("nodes", &vec![node.clone()]);
+        context.insert("root_node", &node);
+        context.insert("config", &config);
+        let (body, status) = render("tree.html", &context, None);
+        assert_eq!(status, 200);
+        assert!(body.contains("A bbP6yFXi2m node with R0pCKXcsvQ."));
+        assert!(!body.contains('`'));
+    }
+
     #[test]
     fn render_custom_error_message() {
         let payload = "dBgIw8DnNHxJojiXzu445qUC4UpxwZCy";
@@ -193,6 +436,114 @@ mod tests {
         assert_eq!(status, 500);
     }
 
+    fn in_directory(directory: &std::path::Path) -> Layers {
+        Layers {
+            templates: vec![directory.to_path_buf()],
+            ..Layers::default()
+        }
+    }
+
+    #[test]
+    fn load_overrides() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-templates-{}", std::process::id()));
+        fs::create_dir_all(directory.join("partials")).unwrap();
+        fs::write(directory.join("partials/note.html"), "Note").unwrap();
+        fs::write(
+            directory.join("about.html"),
+            "{% extends \"base.html\" %}{% block body %}Ours{% endblock %}",
+        )
+        .unwrap();
+
+        let tera = load(&in_directory(&directory)).unwrap();
+        assert!(tera.get_template("about.html").unwrap().path.is_some());
+        assert!(tera.get_template("node.html").unwrap().path.is_none());
+        assert!(tera.get_template("partials/note.html").is_ok());
+
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(
+            load(&Layers::default())
+                .unwrap()
+                .get_template_names()
+                .count(),
+            assets::TEMPLATES.len()
+        );
+    }
+
+    #[test]
+    fn reload() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-reload-{}", std::process::id()));
+        let path = directory.join("reload-4oFMIWlCbx.html");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&path, "First").unwrap();
+
+        let cache: Shared = RwLock::new(None);
+        fill(&cache, &in_directory(&directory), true).unwrap();
+        let context = tera::Context::new();
+        let (first, _) =
+            render_from(&cache, "reload-4oFMIWlCbx.html", &context, None);
+        assert_eq!(first, "First");
+
+        fs::write(&path, "Second").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(1))
+            .unwrap();
+        let (second, _) =
+            render_from(&cache, "reload-4oFMIWlCbx.html", &context, None);
+        assert_eq!(second, "Second");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_template() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-invalid-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("index.html"), "{% if %}").unwrap();
+
+        let cache: Shared = RwLock::new(None);
+        let error = fill(&cache, &in_directory(&directory), false)
+            .err()
+            .unwrap();
+        assert!(describe(&error).contains("index.html"));
+        assert!(describe(&error).contains("expected"));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn template_exists() {
+        assert!(exists("node.html").unwrap());
+        assert!(!exists("fWq1Zs7uTr.html").unwrap());
+    }
+
+    #[test]
+    fn plain_filter_strips_syntax() {
+        let value = tera::Value::from("|Some text|Node and `code`");
+        let plain = plain_filter(&value, &HashMap::new()).unwrap();
+        assert_eq!(plain, tera::Value::from("Some text and code"));
+    }
+
+    #[test]
+    fn plain_filter_unclosed_blocks() {
+        for text in ["## Unclosed header", "Text\n\n`\nunclosed preformat"] {
+            let value = tera::Value::from(text);
+            let plain = plain_filter(&value, &HashMap::new()).unwrap();
+            assert_eq!(plain, value);
+        }
+    }
+
+    #[test]
+    fn plain_filter_rejects_non_strings() {
+        let value = tera::Value::from(1312);
+        assert!(plain_filter(&value, &HashMap::new()).is_err());
+    }
+
     #[test]
     fn emergency_wrap_custom_message() {
         let payload = "JLaTtsnd2IFukIOvqFNymeuiaS6nMaUc";
@@ -1,3 +1,6 @@
+pub mod check;
 pub mod command;
 pub mod content;
+pub mod diff;
+pub mod exchange;
 pub mod serial;
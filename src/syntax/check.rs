@@ -0,0 +1,186 @@
+use std::fmt;
+
+use crate::{syntax::serial::migrate, types::Graph};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something wrong with a graph as written. Errors keep en from serving it
+/// as intended, while warnings point at likely mistakes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{label}: {}", self.message)
+    }
+}
+
+/// Lists the problems in a source graph, as read before deriving edges and
+/// titles, with errors first and then in order of node ID.
+pub fn inspect(graph: &Graph) -> Vec<Problem> {
+    let mut problems: Vec<Problem> = graph
+        .meta
+        .messages
+        .iter()
+        .map(|m| error(m.clone()))
+        .collect();
+
+    if !graph.meta.messages.is_empty() {
+        return problems;
+    }
+
+    if let Some(message) = migrate::warn_newer(graph.meta.version) {
+        problems.push(warning(message));
+    }
+
+    if !graph.nodes.contains_key(&graph.root_node) {
+        problems.push(error(format!(
+            "Root node {} does not exist",
+            graph.root_node
+        )));
+    }
+
+    let mut ids: Vec<&String> = graph.nodes.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let Some(node) = graph.nodes.get(id) else {
+            continue;
+        };
+
+        if !node.id.is_empty() && node.id != *id {
+            problems.push(warning(format!(
+                "Node {id} sets its ID to {}, which is ignored",
+                node.id
+            )));
+        }
+
+        for class in &node.classes {
+            if !is_class_name(class) {
+                problems.push(warning(format!(
+                    "Node {id} has class {class:?}, which is not a valid CSS \
+                        class name"
+                )));
+            }
+        }
+
+        let targets = node
+            .links
+            .iter()
+            .chain(node.connections.iter().flatten().map(|edge| &edge.to));
+        for target in targets {
+            if !graph.nodes.contains_key(target) {
+                problems.push(warning(format!(
+                    "Node {id} has an edge to {target}, which does not exist"
+                )));
+            }
+        }
+    }
+
+    problems.sort_by_key(|problem| problem.severity == Severity::Warning);
+    problems
+}
+
+// Letters, digits, hyphens and underscores, not starting with a digit
+fn is_class_name(class: &str) -> bool {
+    class
+        .chars()
+        .next()
+        .is_some_and(|first| !first.is_ascii_digit())
+        && class
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+fn error(message: String) -> Problem {
+    Problem {
+        severity: Severity::Error,
+        message,
+    }
+}
+
+fn warning(message: String) -> Problem {
+    Problem {
+        severity: Severity::Warning,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems() {
+        let graph: Graph = toml::from_str(
+            r#"
+            root_node = "Missing"
+
+            [nodes.A]
+            id = "B"
+            text = ""
+            links = ["A", "Nowhere"]
+            classes = ["wide", "two words", "3col"]
+            "#,
+        )
+        .unwrap();
+
+        let listed: Vec<String> =
+            inspect(&graph).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            listed,
+            [
+                "error: Root node Missing does not exist",
+                "warning: Node A sets its ID to B, which is ignored",
+                "warning: Node A has class \"two words\", which is not a \
+                    valid CSS class name",
+                "warning: Node A has class \"3col\", which is not a valid \
+                    CSS class name",
+                "warning: Node A has an edge to Nowhere, which does not exist",
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_version() {
+        let graph = migrate::load(
+            "root_node = \"A\"\n\
+            [nodes.A]\ntext = \"\"\nlinks = [\"Nowhere\"]\n\
+            [meta]\nversion = [255, 0, 0]\n",
+        );
+
+        let problems = inspect(&graph);
+        assert!(
+            problems
+                .iter()
+                .all(|problem| problem.severity == Severity::Warning)
+        );
+        let listed: Vec<String> =
+            problems.iter().map(ToString::to_string).collect();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.first().unwrap().contains("Graph version 255.0.0"));
+        assert_eq!(
+            listed.get(1).unwrap(),
+            "warning: Node A has an edge to Nowhere, which does not exist"
+        );
+    }
+
+    #[test]
+    fn unparsable() {
+        let graph = Graph::new(Some("expected `=`"));
+        let problems = inspect(&graph);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems.first().unwrap().severity, Severity::Error);
+    }
+}
@@ -1,12 +1,390 @@
 use std::path::PathBuf;
 
-use crate::prelude::*;
+use crate::syntax::serial::edit::Mutation;
 
+#[expect(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Arguments {
     pub hostname: String,
     pub port: u16,
+    pub socket_path: Option<PathBuf>,
     pub graph_path: PathBuf,
+    pub config_path: Option<PathBuf>,
+    pub settings: Vec<(String, String)>,
+    pub templates_path: Option<PathBuf>,
+    pub static_path: Option<PathBuf>,
+    pub themes_path: PathBuf,
+    pub dev: bool,
+    pub compress: bool,
+    pub gemini_port: Option<u16>,
+    pub gemini_cert: PathBuf,
+    pub gemini_key: PathBuf,
+    pub json: bool,
+    pub hidden: bool,
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Serve,
+    Check,
+    Build {
+        path: PathBuf,
+    },
+    Export {
+        format: String,
+        path: PathBuf,
+    },
+    Import {
+        format: String,
+        paths: Vec<PathBuf>,
+    },
+    Query {
+        text: String,
+    },
+    Mutate(Mutation),
+    Journal {
+        count: usize,
+    },
+    Undo {
+        count: usize,
+    },
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    Migrate,
+}
+
+/// Why the arguments didn't produce a command to run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Usage {
+    /// Help was requested, holding the text to show.
+    Help(String),
+    /// The version was requested, holding the text to show.
+    Version(String),
+    /// The arguments are invalid, holding a message saying why.
+    Invalid(String),
+}
+
+struct Flag {
+    long: &'static str,
+    short: Option<&'static str>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+struct Subcommand {
+    name: &'static str,
+    usage: &'static str,
+    summary: &'static str,
+    flags: &'static [&'static str],
+}
+
+const FLAGS: [Flag; 18] = [
+    Flag {
+        long: "--graph",
+        short: Some("-g"),
+        value: Some("PATH"),
+        help: "Graph file to use [default: ./static/graph.toml]",
+    },
+    Flag {
+        long: "--config",
+        short: None,
+        value: Some("PATH"),
+        help: "Settings file to use [default: ./en.toml, if it exists]",
+    },
+    Flag {
+        long: "--set",
+        short: None,
+        value: Some("KEY=VALUE"),
+        help: "Override a setting, may be repeated",
+    },
+    Flag {
+        long: "--hostname",
+        short: Some("-h"),
+        value: Some("HOST"),
+        help: "Address to listen on [default: 0.0.0.0]",
+    },
+    Flag {
+        long: "--port",
+        short: Some("-p"),
+        value: Some("PORT"),
+        help: "Port to listen on [default: any available port]",
+    },
+    Flag {
+        long: "--socket",
+        short: None,
+        value: Some("PATH"),
+        help: "Listen on a Unix socket at PATH instead of a port",
+    },
+    Flag {
+        long: "--templates",
+        short: None,
+        value: Some("DIR"),
+        help: "Use templates in DIR over the built-in ones",
+    },
+    Flag {
+        long: "--static",
+        short: None,
+        value: Some("DIR"),
+        help: "Use static files in DIR over the built-in ones",
+    },
+    Flag {
+        long: "--themes",
+        short: None,
+        value: Some("DIR"),
+        help: "Directory to look up themes in [default: ./themes]",
+    },
+    Flag {
+        long: "--dev",
+        short: None,
+        value: None,
+        help: "Reload templates from --templates when they change",
+    },
+    Flag {
+        long: "--compress",
+        short: None,
+        value: None,
+        help: "Also write gzip and Brotli copies of text files",
+    },
+    Flag {
+        long: "--gemini-port",
+        short: None,
+        value: Some("PORT"),
+        help: "Also serve Gemini on this port",
+    },
+    Flag {
+        long: "--gemini-cert",
+        short: None,
+        value: Some("PATH"),
+        help: "Gemini TLS certificate [default: ./cert.pem]",
+    },
+    Flag {
+        long: "--gemini-key",
+        short: None,
+        value: Some("PATH"),
+        help: "Gemini TLS private key [default: ./key.pem]",
+    },
+    Flag {
+        long: "--json",
+        short: None,
+        value: None,
+        help: "Print output as JSON",
+    },
+    Flag {
+        long: "--hidden",
+        short: None,
+        value: None,
+        help: "Include hidden nodes",
+    },
+    Flag {
+        long: "--help",
+        short: None,
+        value: None,
+        help: "Show help",
+    },
+    Flag {
+        long: "--version",
+        short: None,
+        value: None,
+        help: "Show the version",
+    },
+];
+
+// Accepted by every subcommand
+const GLOBAL_FLAGS: [&str; 5] =
+    ["--graph", "--config", "--set", "--help", "--version"];
+
+const SUBCOMMANDS: [Subcommand; 13] = [
+    Subcommand {
+        name: "serve",
+        usage: "serve",
+        summary: "Serve the graph over HTTP, the default without a command",
+        flags: &[
+            "--hostname",
+            "--port",
+            "--socket",
+            "--templates",
+            "--static",
+            "--themes",
+            "--dev",
+            "--gemini-port",
+            "--gemini-cert",
+            "--gemini-key",
+        ],
+    },
+    Subcommand {
+        name: "check",
+        usage: "check",
+        summary: "Check the graph for errors and warnings",
+        flags: &[],
+    },
+    Subcommand {
+        name: "build",
+        usage: "build DIR",
+        summary: "Write the site as static files to DIR",
+        flags: &["--templates", "--static", "--themes", "--compress"],
+    },
+    Subcommand {
+        name: "export",
+        usage: "export markdown|csv|tsv PATH",
+        summary: "Write the graph to PATH in another format",
+        flags: &[],
+    },
+    Subcommand {
+        name: "import",
+        usage: "import markdown|csv PATH...",
+        summary: "Print a graph converted from files in another format",
+        flags: &[],
+    },
+    Subcommand {
+        name: "query",
+        usage: "query TEXT",
+        summary: "List nodes whose ID, title or text contain TEXT",
+        flags: &["--json", "--hidden"],
+    },
+    Subcommand {
+        name: "node",
+        usage: "node add ID TEXT | rename FROM TO | delete ID \
+            | set ID FIELD VALUE",
+        summary: "Change a node in the graph file",
+        flags: &[],
+    },
+    Subcommand {
+        name: "edge",
+        usage: "edge add FROM TO [ANCHOR [KIND]] | remove FROM TO",
+        summary: "Change an edge in the graph file",
+        flags: &[],
+    },
+    Subcommand {
+        name: "journal",
+        usage: "journal [COUNT]",
+        summary: "List the latest edits to the graph file",
+        flags: &[],
+    },
+    Subcommand {
+        name: "undo",
+        usage: "undo [COUNT]",
+        summary: "Undo the latest edits to the graph file",
+        flags: &[],
+    },
+    Subcommand {
+        name: "diff",
+        usage: "diff OLD NEW",
+        summary: "Compare two graph files",
+        flags: &["--json"],
+    },
+    Subcommand {
+        name: "migrate",
+        usage: "migrate",
+        summary: "Upgrade the graph file to the current format version",
+        flags: &[],
+    },
+    Subcommand {
+        name: "help",
+        usage: "help [COMMAND]",
+        summary: "Show help for en or one of its commands",
+        flags: &[],
+    },
+];
+
+impl Command {
+    fn from_words(words: &[String]) -> Result<Command, String> {
+        let strings: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        let command = match *strings.as_slice() {
+            [] | ["serve"] => Command::Serve,
+            ["check"] => Command::Check,
+            ["build", path] => Command::Build {
+                path: PathBuf::from(path),
+            },
+            ["export", format, path] => Command::Export {
+                format: format.to_owned(),
+                path: PathBuf::from(path),
+            },
+            ["import", format, ref paths @ ..] if !paths.is_empty() => {
+                Command::Import {
+                    format: format.to_owned(),
+                    paths: paths.iter().map(PathBuf::from).collect(),
+                }
+            },
+            ["query", text] => Command::Query {
+                text: text.to_owned(),
+            },
+            ["node", "add", id, text] => Command::Mutate(Mutation::AddNode {
+                id: id.to_owned(),
+                text: text.to_owned(),
+            }),
+            ["node", "rename", from, to] => {
+                Command::Mutate(Mutation::RenameNode {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                })
+            },
+            ["node", "delete", id] => {
+                Command::Mutate(Mutation::DeleteNode { id: id.to_owned() })
+            },
+            ["node", "set", id, field, value] => {
+                Command::Mutate(Mutation::SetField {
+                    id: id.to_owned(),
+                    field: field.to_owned(),
+                    value: value.to_owned(),
+                })
+            },
+            ["edge", "add", from, to, ref rest @ ..] if rest.len() <= 2 => {
+                Command::Mutate(Mutation::AddEdge {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                    anchor: rest
+                        .first()
+                        .copied()
+                        .unwrap_or_default()
+                        .to_owned(),
+                    kind: rest.get(1).copied().unwrap_or_default().to_owned(),
+                })
+            },
+            ["edge", "remove", from, to] => {
+                Command::Mutate(Mutation::RemoveEdge {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                })
+            },
+            ["journal"] => Command::Journal { count: 10 },
+            ["journal", count] => Command::Journal {
+                count: parse_count(count)?,
+            },
+            ["undo"] => Command::Undo { count: 1 },
+            ["undo", count] => Command::Undo {
+                count: parse_count(count)?,
+            },
+            ["diff", old, new] => Command::Diff {
+                old: PathBuf::from(old),
+                new: PathBuf::from(new),
+            },
+            ["migrate"] => Command::Migrate,
+            [name, ..] => {
+                return Err(find_subcommand(name).map_or_else(
+                    || format!("Unrecognized command {name:?}"),
+                    |subcommand| {
+                        format!(
+                            "Invalid arguments for {name}, expected: en {}",
+                            subcommand.usage
+                        )
+                    },
+                ));
+            },
+        };
+
+        Ok(command)
+    }
+}
+
+fn parse_count(count: &str) -> Result<usize, String> {
+    count
+        .parse()
+        .map_err(|e| format!("Invalid count {count:?}: {e}"))
 }
 
 impl Arguments {
@@ -14,63 +392,350 @@ impl Arguments {
         format!("{}:{}", self.hostname, self.port)
     }
 
+    pub fn make_gemini_address(&self) -> Option<String> {
+        self.gemini_port
+            .map(|port| format!("{}:{}", self.hostname, port))
+    }
+
     pub fn new() -> Arguments {
         Arguments {
             hostname: String::from("0.0.0.0"),
             port: 0,
+            socket_path: None,
             graph_path: PathBuf::from("./static/graph.toml"),
+            config_path: None,
+            settings: vec![],
+            templates_path: None,
+            static_path: None,
+            themes_path: PathBuf::from("./themes"),
+            dev: false,
+            compress: false,
+            gemini_port: None,
+            gemini_cert: PathBuf::from("./cert.pem"),
+            gemini_key: PathBuf::from("./key.pem"),
+            json: false,
+            hidden: false,
+            command: Command::Serve,
         }
     }
 
+    /// Reads the options it recognizes from the process arguments, ignoring
+    /// anything else, so settings like the graph path can be looked up from
+    /// anywhere without failing.
     #[must_use]
     pub fn parse(&self) -> Arguments {
+        let args: Vec<String> = std::env::args().collect();
+        parse_known(self, &args)
+    }
+
+    /// Reads the process arguments, rejecting any that aren't valid.
+    ///
+    /// # Errors
+    /// Returns [`Usage`] if help or the version were requested or if the
+    /// arguments are invalid.
+    pub fn try_parse(&self) -> Result<Arguments, Usage> {
         let args: Vec<String> = std::env::args().collect();
         parse(self, &args)
     }
 }
 
-fn parse(defaults: &Arguments, args: &[String]) -> Arguments {
-    let mut out_args = defaults.clone();
+fn parse(defaults: &Arguments, args: &[String]) -> Result<Arguments, Usage> {
+    let (words, flags) = split(args, true)?;
+    let name = words.first().map_or("serve", String::as_str);
+
+    if flags.iter().any(|&(flag, _)| flag.long == "--help") || name == "help" {
+        let topic = if name == "help" {
+            words.get(1)
+        } else {
+            words.first()
+        };
+        return Err(match topic {
+            Some(requested) => find_subcommand(requested).map_or_else(
+                || {
+                    Usage::Invalid(format!(
+                        "Unrecognized command {requested:?}"
+                    ))
+                },
+                |subcommand| Usage::Help(make_help(subcommand)),
+            ),
+            None => Usage::Help(make_overview()),
+        });
+    }
+
+    if flags.iter().any(|&(flag, _)| flag.long == "--version") {
+        return Err(Usage::Version(format!(
+            "en {}\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+
+    let command = Command::from_words(&words).map_err(Usage::Invalid)?;
+
+    let allowed = find_subcommand(name).map_or(&[][..], |s| s.flags);
+    for &(flag, _) in &flags {
+        if !GLOBAL_FLAGS.contains(&flag.long) && !allowed.contains(&flag.long) {
+            return Err(Usage::Invalid(format!(
+                "Option {} can't be used with {name}",
+                flag.long
+            )));
+        }
+    }
+
+    let mut out_args = Arguments {
+        command,
+        ..defaults.clone()
+    };
+    for (flag, value) in flags {
+        apply(&mut out_args, flag, &value.unwrap_or_default())
+            .map_err(Usage::Invalid)?;
+    }
+
+    Ok(out_args)
+}
+
+// Like `parse`, but skipping anything it can't make sense of, such as the
+// arguments given to the test harness
+fn parse_known(defaults: &Arguments, args: &[String]) -> Arguments {
+    let Ok((words, flags)) = split(args, false) else {
+        return defaults.clone();
+    };
+
+    let mut out_args = Arguments {
+        command: Command::from_words(&words).unwrap_or_default(),
+        ..defaults.clone()
+    };
+    for (flag, value) in flags {
+        let _ignored = apply(&mut out_args, flag, &value.unwrap_or_default());
+    }
+
+    out_args
+}
+
+type Flags = Vec<(&'static Flag, Option<String>)>;
 
+// Separates words from flags and their values. Flags may come before or
+// after the words, and values may be given as `--flag value` or
+// `--flag=value`.
+fn split(args: &[String], strict: bool) -> Result<(Vec<String>, Flags), Usage> {
+    // The first argument is the program, unless arguments were given
+    // without it
     let filtered_args = if let Some((head, tail)) = args.split_first() {
         if head.starts_with('-') { args } else { tail }
     } else {
         args
     };
 
-    for arg in filtered_args.chunks(2) {
-        if let Some(argument) = arg.first()
-            && let Some(parameter) = arg.get(1)
-        {
-            if argument.eq("-h") || argument.eq("--hostname") {
-                out_args.hostname = String::from(parameter);
-            } else if argument.eq("-p") || argument.eq("--port") {
-                out_args.port = parameter.parse().unwrap_or(out_args.port);
-            } else if argument.eq("-g") || argument.eq("--graph") {
-                out_args.graph_path = PathBuf::from(parameter);
-            } else {
-                log!("Dropped unrecognized argument {argument}");
-            }
-        } else {
-            panic!("Argument {arg:?} has no corresponding value")
+    let mut words = vec![];
+    let mut flags: Flags = vec![];
+    let mut only_words = false;
+    let mut remaining = filtered_args.iter();
+
+    while let Some(arg) = remaining.next() {
+        if only_words || !arg.starts_with('-') || arg == "-" {
+            words.push(arg.clone());
+            continue;
+        }
+
+        if arg == "--" {
+            only_words = true;
+            continue;
         }
+
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if arg.starts_with("--") => {
+                (name, Some(value.to_owned()))
+            },
+            _ => (arg.as_str(), None),
+        };
+
+        let Some(flag) = FLAGS
+            .iter()
+            .find(|flag| flag.long == name || flag.short == Some(name))
+        else {
+            if strict {
+                return Err(Usage::Invalid(format!(
+                    "Unrecognized option {name}"
+                )));
+            }
+            continue;
+        };
+
+        let value = match (flag.value, inline_value) {
+            (None, None) => None,
+            (None, Some(_)) => {
+                if strict {
+                    return Err(Usage::Invalid(format!(
+                        "Option {} doesn't take a value",
+                        flag.long
+                    )));
+                }
+                continue;
+            },
+            (Some(_), Some(value)) => Some(value),
+            (Some(placeholder), None) => {
+                if let Some(value) = remaining.next() {
+                    Some(value.clone())
+                } else if strict {
+                    return Err(Usage::Invalid(format!(
+                        "Option {} needs a value: {} {placeholder}",
+                        flag.long, flag.long
+                    )));
+                } else {
+                    continue;
+                }
+            },
+        };
+
+        flags.push((flag, value));
     }
-    out_args
+
+    Ok((words, flags))
+}
+
+fn apply(args: &mut Arguments, flag: &Flag, value: &str) -> Result<(), String> {
+    let invalid = |e: std::num::ParseIntError| {
+        format!("Invalid value {value:?} for {}: {e}", flag.long)
+    };
+
+    match flag.long {
+        "--graph" => args.graph_path = PathBuf::from(value),
+        "--config" => args.config_path = Some(PathBuf::from(value)),
+        "--set" => {
+            let Some((key, setting)) = value.split_once('=') else {
+                return Err(format!(
+                    "Invalid value {value:?} for --set, expected KEY=VALUE"
+                ));
+            };
+            args.settings
+                .push((key.trim().to_owned(), setting.to_owned()));
+        },
+        "--templates" => args.templates_path = Some(PathBuf::from(value)),
+        "--static" => args.static_path = Some(PathBuf::from(value)),
+        "--hostname" => value.clone_into(&mut args.hostname),
+        "--port" => args.port = value.parse().map_err(invalid)?,
+        "--socket" => args.socket_path = Some(PathBuf::from(value)),
+        "--gemini-port" => {
+            args.gemini_port = Some(value.parse().map_err(invalid)?);
+        },
+        "--gemini-cert" => args.gemini_cert = PathBuf::from(value),
+        "--gemini-key" => args.gemini_key = PathBuf::from(value),
+        "--themes" => args.themes_path = PathBuf::from(value),
+        "--dev" => args.dev = true,
+        "--compress" => args.compress = true,
+        "--json" => args.json = true,
+        "--hidden" => args.hidden = true,
+        _ => {},
+    }
+
+    Ok(())
+}
+
+fn find_subcommand(name: &str) -> Option<&'static Subcommand> {
+    SUBCOMMANDS
+        .iter()
+        .find(|subcommand| subcommand.name == name)
+}
+
+fn make_overview() -> String {
+    let commands: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|subcommand| {
+            format!("  {:<10}{}", subcommand.name, subcommand.summary)
+        })
+        .collect();
+
+    format!(
+        "en {}: {}\n\n\
+        Usage: en [COMMAND] [OPTIONS]\n\n\
+        Commands:\n{}\n\n\
+        Options:\n{}\n\n\
+        Run 'en COMMAND --help' for the options of a command.\n",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_DESCRIPTION"),
+        commands.join("\n"),
+        list_flags(&GLOBAL_FLAGS),
+    )
+}
+
+fn make_help(subcommand: &Subcommand) -> String {
+    let names: Vec<&str> = subcommand
+        .flags
+        .iter()
+        .chain(&GLOBAL_FLAGS)
+        .copied()
+        .collect();
+
+    format!(
+        "Usage: en {} [OPTIONS]\n\n{}.\n\nOptions:\n{}\n",
+        subcommand.usage,
+        subcommand.summary,
+        list_flags(&names),
+    )
+}
+
+fn list_flags(names: &[&str]) -> String {
+    FLAGS
+        .iter()
+        .filter(|flag| names.contains(&flag.long))
+        .map(|flag| {
+            let short = flag
+                .short
+                .map_or(String::from("    "), |short| format!("{short}, "));
+            let value = flag.value.map_or(String::new(), |v| format!(" {v}"));
+            let name = format!("{short}{}{value}", flag.long);
+            format!("  {name:<24}{}", flag.help)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn words(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    fn invalid(line: &str) -> String {
+        match parse(&Arguments::new(), &words(line)) {
+            Err(Usage::Invalid(message)) => message,
+            other => panic!("Expected {line:?} to be invalid, got {other:?}"),
+        }
+    }
+
     #[test]
     fn address() {
         let args = Arguments {
             hostname: String::from("localhost"),
             port: 3007,
             graph_path: PathBuf::new(),
+            ..Arguments::new()
         };
 
         assert_eq!(args.make_address(), "localhost:3007");
+        assert!(args.make_gemini_address().is_none());
+    }
+
+    #[test]
+    fn gemini() {
+        let defaults = Arguments::new();
+
+        let args = parse(
+            &defaults,
+            &[
+                String::from("--gemini-port"),
+                String::from("1965"),
+                String::from("--gemini-cert"),
+                String::from("/tmp/cert.pem"),
+                String::from("--gemini-key"),
+                String::from("/tmp/key.pem"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(args.make_gemini_address().unwrap(), "0.0.0.0:1965");
+        assert_eq!(args.gemini_cert, PathBuf::from("/tmp/cert.pem"));
+        assert_eq!(args.gemini_key, PathBuf::from("/tmp/key.pem"));
     }
 
     #[test]
@@ -79,7 +744,8 @@ mod tests {
 
         let payload = String::from("olUCu7vWcUAsumv2xpj2Z55EDheWLTEu");
         let args =
-            parse(&defaults, &[String::from("-h"), String::from(&payload)]);
+            parse(&defaults, &[String::from("-h"), String::from(&payload)])
+                .unwrap();
         assert_eq!(args.hostname, payload);
     }
 
@@ -88,10 +754,22 @@ mod tests {
         let defaults = Arguments::new();
 
         let payload = 3901;
-        let args = parse(&defaults, &[String::from("-p"), payload.to_string()]);
+        let args = parse(&defaults, &[String::from("-p"), payload.to_string()])
+            .unwrap();
         assert_eq!(args.port, payload);
     }
 
+    #[test]
+    fn socket() {
+        let args = parse(&Arguments::new(), &words("en --socket /run/en.sock"))
+            .unwrap();
+        assert_eq!(args.socket_path, Some(PathBuf::from("/run/en.sock")));
+        assert_eq!(
+            invalid("en build ./site --socket /run/en.sock"),
+            "Option --socket can't be used with build"
+        );
+    }
+
     #[test]
     fn graph_path() {
         let defaults = Arguments::new();
@@ -100,15 +778,264 @@ mod tests {
         let args = parse(
             &defaults,
             &[String::from("-g"), payload.to_str().unwrap().to_string()],
-        );
+        )
+        .unwrap();
         assert_eq!(args.graph_path, payload);
+
+        let inline = parse(&defaults, &words("en --graph=/tmp/")).unwrap();
+        assert_eq!(inline.graph_path, payload);
+    }
+
+    #[test]
+    fn export_command() {
+        let args = parse(
+            &Arguments::new(),
+            &words("en export markdown /tmp/vault -g graph.toml"),
+        )
+        .unwrap();
+        assert_eq!(
+            args.command,
+            Command::Export {
+                format: String::from("markdown"),
+                path: PathBuf::from("/tmp/vault"),
+            }
+        );
+        assert_eq!(args.graph_path, PathBuf::from("graph.toml"));
+    }
+
+    #[test]
+    fn import_command() {
+        let args =
+            parse(&Arguments::new(), &words("en import markdown ./notes"))
+                .unwrap();
+        assert_eq!(
+            args.command,
+            Command::Import {
+                format: String::from("markdown"),
+                paths: vec![PathBuf::from("./notes")],
+            }
+        );
+    }
+
+    #[test]
+    fn mutate_commands() {
+        assert_eq!(
+            parse(&Arguments::new(), &words("en node rename A B -g x.toml"))
+                .unwrap(),
+            Arguments {
+                command: Command::Mutate(Mutation::RenameNode {
+                    from: String::from("A"),
+                    to: String::from("B"),
+                }),
+                graph_path: PathBuf::from("x.toml"),
+                ..Arguments::new()
+            }
+        );
+
+        assert_eq!(
+            parse(&Arguments::new(), &words("en edge add A B"))
+                .unwrap()
+                .command,
+            Command::Mutate(Mutation::AddEdge {
+                from: String::from("A"),
+                to: String::from("B"),
+                anchor: String::new(),
+                kind: String::new(),
+            })
+        );
+
+        assert_eq!(
+            parse(&Arguments::new(), &words("en edge add A B b part_of"))
+                .unwrap()
+                .command,
+            Command::Mutate(Mutation::AddEdge {
+                from: String::from("A"),
+                to: String::from("B"),
+                anchor: String::from("b"),
+                kind: String::from("part_of"),
+            })
+        );
+
+        // Text starting with a dash can follow `--`
+        assert_eq!(
+            parse(&Arguments::new(), &words("en node add A -- -1"))
+                .unwrap()
+                .command,
+            Command::Mutate(Mutation::AddNode {
+                id: String::from("A"),
+                text: String::from("-1"),
+            })
+        );
+    }
+
+    #[test]
+    fn journal_commands() {
+        assert_eq!(
+            parse(&Arguments::new(), &words("en journal"))
+                .unwrap()
+                .command,
+            Command::Journal { count: 10 }
+        );
+        assert_eq!(
+            parse(&Arguments::new(), &words("en undo 3"))
+                .unwrap()
+                .command,
+            Command::Undo { count: 3 }
+        );
+    }
+
+    #[test]
+    fn boolean_flags() {
+        let diff =
+            parse(&Arguments::new(), &words("en diff --json a.toml b.toml"))
+                .unwrap();
+        assert_eq!(
+            diff.command,
+            Command::Diff {
+                old: PathBuf::from("a.toml"),
+                new: PathBuf::from("b.toml"),
+            }
+        );
+        assert!(diff.json);
+
+        let query = parse(&Arguments::new(), &words("en query quark --hidden"))
+            .unwrap();
+        assert!(query.hidden && !query.json);
+    }
+
+    #[test]
+    fn help() {
+        let Err(Usage::Help(overview)) =
+            parse(&Arguments::new(), &words("en --help"))
+        else {
+            panic!("Expected help");
+        };
+        assert!(overview.contains("Usage: en [COMMAND] [OPTIONS]"));
+        assert!(overview.contains("  query     List nodes"));
+
+        let Err(Usage::Help(query)) =
+            parse(&Arguments::new(), &words("en help query"))
+        else {
+            panic!("Expected help");
+        };
+        assert!(query.starts_with("Usage: en query TEXT [OPTIONS]\n"));
+        assert!(
+            query.contains("      --hidden            Include hidden nodes")
+        );
+        assert!(query.contains("  -g, --graph PATH"));
+        assert!(!query.contains("--port"));
+
+        assert_eq!(
+            parse(&Arguments::new(), &words("en diff --version")),
+            Err(Usage::Version(format!(
+                "en {}\n",
+                env!("CARGO_PKG_VERSION")
+            )))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert_eq!(
+            invalid("en undo x"),
+            "Invalid count \"x\": invalid digit found in string"
+        );
+        assert_eq!(
+            invalid("en Ir4uDJ2wbQ"),
+            "Unrecognized command \"Ir4uDJ2wbQ\""
+        );
+        assert_eq!(
+            invalid("en export markdown"),
+            "Invalid arguments for export, expected: en export markdown|csv|tsv PATH"
+        );
+        assert_eq!(invalid("en --verbose"), "Unrecognized option --verbose");
+        assert_eq!(
+            invalid("en --port"),
+            "Option --port needs a value: --port PORT"
+        );
+        assert_eq!(
+            invalid("en --port 80a"),
+            "Invalid value \"80a\" for --port: invalid digit found in string"
+        );
+        assert_eq!(
+            invalid("en --json=yes"),
+            "Option --json doesn't take a value"
+        );
+        assert_eq!(
+            invalid("en check --port 80"),
+            "Option --port can't be used with check"
+        );
+        assert_eq!(
+            invalid("en help Ir4uDJ2wbQ"),
+            "Unrecognized command \"Ir4uDJ2wbQ\""
+        );
+    }
+
+    #[test]
+    fn settings() {
+        let args = parse(
+            &Arguments::new(),
+            &words("en --set tree=false --config=staging.toml --set a=b=c"),
+        )
+        .unwrap();
+        assert_eq!(args.config_path, Some(PathBuf::from("staging.toml")));
+        assert_eq!(
+            args.settings,
+            [
+                (String::from("tree"), String::from("false")),
+                (String::from("a"), String::from("b=c")),
+            ]
+        );
+
+        assert_eq!(
+            invalid("en --set tree"),
+            "Invalid value \"tree\" for --set, expected KEY=VALUE"
+        );
+    }
+
+    #[test]
+    fn asset_directories() {
+        let args = parse(
+            &Arguments::new(),
+            &words("en build ./site --templates ./theme --static=./assets"),
+        )
+        .unwrap();
+        assert_eq!(args.templates_path, Some(PathBuf::from("./theme")));
+        assert_eq!(args.static_path, Some(PathBuf::from("./assets")));
+        assert!(!args.dev && !args.compress);
+
+        let serve = parse(&Arguments::new(), &words("en --dev --templates .."));
+        assert!(serve.unwrap().dev);
+
+        assert_eq!(
+            invalid("en check --templates ./theme"),
+            "Option --templates can't be used with check"
+        );
+
+        let build =
+            parse(&Arguments::new(), &words("en build ./site --compress"));
+        assert!(build.unwrap().compress);
+        assert_eq!(
+            invalid("en --compress"),
+            "Option --compress can't be used with serve"
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let args = parse_known(
+            &Arguments::new(),
+            &words("en-5f0c syntax:: --test-threads 4 --nocapture -g x.toml"),
+        );
+        assert_eq!(args.graph_path, PathBuf::from("x.toml"));
+        assert_eq!(args.command, Command::Serve);
     }
 
     #[test]
     fn empty() {
         let defaults = Arguments::new();
 
-        let args = parse(&defaults, &[]);
+        let args = parse(&defaults, &[]).unwrap();
         assert_eq!(defaults, args);
     }
 }
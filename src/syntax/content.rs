@@ -1,11 +1,12 @@
 use parser::{token::Token, lexeme::Lexeme};
+use render::Renderer;
 
 pub mod parser;
+pub mod render;
 
 pub trait Parseable {
     fn probe(lexeme: &Lexeme) -> bool;
     fn lex(lexeme: &Lexeme) -> Self;
-    fn render(&self) -> String;
 }
 
 type Probe = fn(&Lexeme) -> bool;
@@ -15,3 +16,19 @@ type LexMap<'lm> = &'lm [(Probe, Lexer)];
 pub fn parse(text: &str) -> String {
     parser::read(text)
 }
+
+pub fn plain(text: &str) -> String {
+    parser::read_plain(text)
+}
+
+pub fn summarize(text: &str) -> String {
+    parser::summarize(text)
+}
+
+pub fn tokenize(text: &str) -> Vec<Token> {
+    parser::tokenize(text)
+}
+
+pub fn render<R: Renderer>(text: &str, renderer: &mut R) -> String {
+    renderer.render(&tokenize(text))
+}
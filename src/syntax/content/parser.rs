@@ -1,7 +1,10 @@
 use std::collections::{HashMap};
 
 use crate::{syntax::serial::populate_graph, types::Config};
-use super::{Parseable as _, Token, LexMap};
+use super::{
+    Parseable as _, Token, LexMap,
+    render::{Renderer as _, html::Html, plain::Plain},
+};
 use token::{
     anchor::Anchor, linebreak::LineBreak, paragraph::Paragraph, header::Header,
     preformat::PreFormat, literal::Literal, code::Code,
@@ -243,12 +246,29 @@ fn close(state: &State, tokens: &mut Vec<Token>) {
     }
 }
 
-fn parse(tokens: &[Token]) -> String {
-    tokens.iter().map(Token::render).collect::<String>()
+pub(super) fn tokenize(text: &str) -> Vec<Token> {
+    lex(text, LEXMAP)
 }
 
 pub(super) fn read(text: &str) -> String {
-    parse(&lex(text, LEXMAP))
+    Html.render(&tokenize(text))
+}
+
+pub(super) fn read_plain(text: &str) -> String {
+    Plain.render(&tokenize(text)).trim().to_owned()
+}
+
+// Plain text of the first paragraph, skipping any markup
+pub(super) fn summarize(text: &str) -> String {
+    tokenize(text)
+        .iter()
+        .skip_while(|token| !matches!(token, Token::Paragraph(_)))
+        .skip(1)
+        .take_while(|token| !matches!(token, Token::Paragraph(_)))
+        .map(|token| Plain.token(token))
+        .collect::<String>()
+        .trim()
+        .to_owned()
 }
 
 #[cfg(test)]
@@ -303,6 +323,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn plain_strips_syntax() {
+        assert_eq!(
+            read_plain(
+                "## Title\nSee |this node|Node|, `code` and x|Y|.\n`\npre\n`\n"
+            ),
+            "Title\nSee this node, code and x.\n\npre"
+        );
+    }
+
+    #[test]
+    fn summarize_first_paragraph() {
+        assert_eq!(
+            summarize("`\npre\n`\n\nSee |this node|Node|, `now`.\nSecond."),
+            "See this node, now."
+        );
+    }
+
+    #[test]
+    fn summarize_empty() {
+        assert_eq!(summarize("## Only a header\n"), "");
+    }
+
     #[test]
     #[should_panic(expected = "End of file with open header")]
     fn end_with_open_header() {
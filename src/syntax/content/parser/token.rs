@@ -1,5 +1,3 @@
-use crate::syntax::content::Parseable as _;
-
 pub mod literal;
 pub mod anchor;
 pub mod linebreak;
@@ -21,21 +19,6 @@ pub enum Token {
     Span(span::Span),
 }
 
-impl Token {
-    pub fn render(&self) -> String {
-        match *self {
-            Token::Anchor(ref d) => d.render(),
-            Token::Code(ref d) => d.render(),
-            Token::Header(ref d) => d.render(),
-            Token::LineBreak(ref d) => d.render(),
-            Token::Literal(ref d) => d.render(),
-            Token::Paragraph(ref d) => d.render(),
-            Token::PreFormat(ref d) => d.render(),
-            Token::Span(ref d) => d.render(),
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
 
@@ -15,26 +15,6 @@ impl Parseable for Anchor {
     fn lex(_lexeme: &Lexeme) -> Anchor {
         panic!("Attempt to lex an anchor directly from a lexeme");
     }
-
-    fn render(&self) -> String {
-        let Some(ref destination) = self.destination else {
-            panic!(
-                "Attempt to render anchor {self:?} without knowing its destination."
-            )
-        };
-
-        let non_empty_destination = if destination.is_empty() {
-            self.text.clone()
-        } else {
-            destination.to_owned()
-        };
-
-        format!(
-            r#"<a href="{}">{}</a>"#,
-            Anchor::resolve_destination(&non_empty_destination),
-            &self.text
-        )
-    }
 }
 
 impl Anchor {
@@ -46,8 +26,34 @@ impl Anchor {
         }
     }
 
+    /// Destination as written, falling back to the anchor text if empty.
+    pub fn target(&self) -> Option<String> {
+        self.destination.as_ref().map(|destination| {
+            if destination.is_empty() {
+                self.text.clone()
+            } else {
+                destination.clone()
+            }
+        })
+    }
+
+    /// Target resolved to an address, with node IDs under `/node/`.
+    pub fn href(&self) -> Option<String> {
+        self.target()
+            .map(|target| Anchor::resolve_destination(&target))
+    }
+
+    /// Node ID this anchor points to, unless its target is an address.
+    pub fn node_id(&self) -> Option<String> {
+        self.target().filter(|target| !Anchor::is_address(target))
+    }
+
+    fn is_address(raw: &str) -> bool {
+        raw.contains(':') || raw.contains('/')
+    }
+
     fn resolve_destination(raw: &str) -> String {
-        if raw.contains(":") || raw.contains("/") {
+        if Anchor::is_address(raw) {
             raw.to_owned()
         } else {
             format!("/node/{raw}")
@@ -69,12 +75,24 @@ mod tests {
     use super::*;
 
     #[test]
-    fn render_anchor() {
-        let anchor = Anchor::new("AnchorText", "AnchorDest", true);
-        assert_eq!(
-            anchor.render(),
-            r#"<a href="/node/AnchorDest">AnchorText</a>"#
-        );
+    fn node_id() {
+        let anchor = Anchor {
+            destination: Some(String::from("AnchorDest")),
+            ..Anchor::empty()
+        };
+        assert_eq!(anchor.node_id(), Some(String::from("AnchorDest")));
+        assert_eq!(anchor.href(), Some(String::from("/node/AnchorDest")));
+    }
+
+    #[test]
+    fn address_is_not_node_id() {
+        let anchor = Anchor {
+            text: String::from("https://example.com"),
+            destination: Some(String::new()),
+            leading: true,
+        };
+        assert!(anchor.node_id().is_none());
+        assert_eq!(anchor.href(), Some(String::from("https://example.com")));
     }
 
     #[test]
@@ -84,11 +102,4 @@ mod tests {
     fn lex() {
         Anchor::lex(&Lexeme::new("", ""));
     }
-
-    #[test]
-    #[should_panic(expected = "without knowing its destination")]
-    fn unknown_destination_render() {
-        let anchor = Anchor::empty();
-        drop(anchor.render());
-    }
 }
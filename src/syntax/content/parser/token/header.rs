@@ -60,6 +60,10 @@ impl Header {
         }
     }
 
+    pub fn open(&self) -> Option<bool> {
+        self.open
+    }
+
     pub fn level(&self) -> u8 {
         match self.level {
             Level::One => 1,
@@ -95,20 +99,6 @@ impl Parseable for Header {
             Some(&lexeme.next.to_ascii_lowercase()),
         )
     }
-
-    fn render(&self) -> String {
-        if let Some(open) = self.open {
-            if open && let Some(ref id) = self.dom_id {
-                format!(r#"<h{} id="{}">"#, self.level, id)
-            } else if open {
-                format!("<h{}>", self.level)
-            } else {
-                format!("</h{}>", self.level)
-            }
-        } else {
-            panic!("Attempt to render a header tag while open state is unknown")
-        }
-    }
 }
 
 #[derive(Debug)]
@@ -167,6 +157,8 @@ impl Display for Level {
 
 #[cfg(test)]
 mod tests {
+    use crate::syntax::content::render::{Renderer as _, html::Html};
+
     use super::*;
 
     #[test]
@@ -235,14 +227,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn no_id_render() {
-        let open_header = Header::from_u8(2, true, None);
-        let closed_header = Header::from_u8(2, false, None);
-        assert_eq!(open_header.render(), "<h2>");
-        assert_eq!(closed_header.render(), "</h2>");
-    }
-
     #[test]
     #[should_panic(
         expected = "Attempt to render a header tag while open state is unknown"
@@ -254,6 +238,6 @@ mod tests {
             dom_id: None,
         };
 
-        header.render();
+        drop(Html.header(&header));
     }
 }
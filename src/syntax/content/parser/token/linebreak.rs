@@ -13,8 +13,4 @@ impl Parseable for LineBreak {
     fn lex(_lexeme: &Lexeme) -> LineBreak {
         LineBreak {}
     }
-
-    fn render(&self) -> String {
-        "\n".to_owned()
-    }
 }
@@ -5,6 +5,12 @@ pub struct Literal {
     text: String,
 }
 
+impl Literal {
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+}
+
 impl Parseable for Literal {
     fn probe(_lexeme: &Lexeme) -> bool {
         true
@@ -15,8 +21,4 @@ impl Parseable for Literal {
             text: lexeme.text(),
         }
     }
-
-    fn render(&self) -> String {
-        self.text.clone()
-    }
 }
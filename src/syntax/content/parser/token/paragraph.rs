@@ -9,6 +9,10 @@ impl Paragraph {
     pub fn new(open: bool) -> Paragraph {
         Paragraph { open: Some(open) }
     }
+
+    pub fn open(&self) -> Option<bool> {
+        self.open
+    }
 }
 
 impl Parseable for Paragraph {
@@ -20,20 +24,6 @@ impl Parseable for Paragraph {
     fn lex(_lexeme: &Lexeme) -> Paragraph {
         Paragraph { open: None }
     }
-
-    fn render(&self) -> String {
-        if let Some(open) = self.open {
-            if open {
-                "<p>".to_owned()
-            } else {
-                "</p>".to_owned()
-            }
-        } else {
-            panic!(
-                "Attempt to render a paragraph tag while open state is unknown"
-            )
-        }
-    }
 }
 
 #[cfg(test)]
@@ -45,13 +35,4 @@ mod tests {
         let p = Paragraph::lex(&Lexeme::new("", ""));
         assert!(p.open.is_none());
     }
-
-    #[test]
-    #[should_panic(
-        expected = "Attempt to render a paragraph tag while open state is unknown"
-    )]
-    fn render_state_unknown() {
-        let p = Paragraph::lex(&Lexeme::new("", ""));
-        drop(p.render());
-    }
 }
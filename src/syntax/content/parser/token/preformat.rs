@@ -11,6 +11,10 @@ impl PreFormat {
     pub fn new(open: bool) -> PreFormat {
         PreFormat { open: Some(open) }
     }
+
+    pub fn open(&self) -> Option<bool> {
+        self.open
+    }
 }
 
 impl Parseable for PreFormat {
@@ -21,20 +25,6 @@ impl Parseable for PreFormat {
     fn lex(_lexeme: &Lexeme) -> PreFormat {
         PreFormat { open: None }
     }
-
-    fn render(&self) -> String {
-        if let Some(o) = self.open {
-            if o {
-                "<pre>".to_owned()
-            } else {
-                "</pre>".to_owned()
-            }
-        } else {
-            panic!(
-                "Attempt to render a preformat tag while open state is unknown"
-            )
-        }
-    }
 }
 
 #[cfg(test)]
@@ -49,16 +39,4 @@ mod tests {
         let from_non_empty_lexeme = PreFormat::lex(&Lexeme::new("`", "`"));
         assert!(from_non_empty_lexeme.open.is_none());
     }
-
-    #[test]
-    #[should_panic(
-        expected = "Attempt to render a preformat tag while open state is unknown"
-    )]
-    fn render() {
-        let from_empty_lexeme = PreFormat::lex(&Lexeme::new("", ""));
-        from_empty_lexeme.render();
-
-        let from_non_empty_lexeme = PreFormat::lex(&Lexeme::new("`", "`"));
-        from_non_empty_lexeme.render();
-    }
 }
@@ -9,6 +9,10 @@ impl Span {
     pub fn new(open: bool) -> Span {
         Span { open: Some(open) }
     }
+
+    pub fn open(&self) -> Option<bool> {
+        self.open
+    }
 }
 
 impl Parseable for Span {
@@ -20,18 +24,6 @@ impl Parseable for Span {
     fn lex(_lexeme: &Lexeme) -> Span {
         Span { open: None }
     }
-
-    fn render(&self) -> String {
-        if let Some(open) = self.open {
-            if open {
-                "<span>".to_owned()
-            } else {
-                "</span>".to_owned()
-            }
-        } else {
-            panic!("Attempt to render a span tag while open state is unknown")
-        }
-    }
 }
 
 #[cfg(test)]
@@ -54,22 +46,4 @@ mod tests {
         ));
         assert!(span.open.is_none());
     }
-
-    #[test]
-    fn render() {
-        let open_span = Span::new(true);
-        assert_eq!(open_span.render(), "<span>");
-
-        let closed_span = Span::new(false);
-        assert_eq!(closed_span.render(), "</span>");
-    }
-
-    #[test]
-    #[should_panic(
-        expected = "Attempt to render a span tag while open state is unknown"
-    )]
-    fn render_unknown_open_state() {
-        let open_span = Span::lex(&Lexeme::new("", ""));
-        drop(open_span.render());
-    }
 }
@@ -0,0 +1,42 @@
+use super::parser::token::{
+    Token, anchor::Anchor, code::Code, header::Header, linebreak::LineBreak,
+    literal::Literal, paragraph::Paragraph, preformat::PreFormat, span::Span,
+};
+
+pub mod html;
+pub mod plain;
+pub mod markdown;
+pub mod gemtext;
+
+/// An output backend for a token stream.
+///
+/// Each method renders a single token. Renderers that need to look beyond
+/// one token, such as to collect links, can keep state between calls and
+/// override `render` to flush it.
+pub trait Renderer {
+    fn anchor(&mut self, anchor: &Anchor) -> String;
+    fn code(&mut self, code: &Code) -> String;
+    fn header(&mut self, header: &Header) -> String;
+    fn linebreak(&mut self, linebreak: &LineBreak) -> String;
+    fn literal(&mut self, literal: &Literal) -> String;
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String;
+    fn preformat(&mut self, preformat: &PreFormat) -> String;
+    fn span(&mut self, span: &Span) -> String;
+
+    fn token(&mut self, token: &Token) -> String {
+        match *token {
+            Token::Anchor(ref t) => self.anchor(t),
+            Token::Code(ref t) => self.code(t),
+            Token::Header(ref t) => self.header(t),
+            Token::LineBreak(ref t) => self.linebreak(t),
+            Token::Literal(ref t) => self.literal(t),
+            Token::Paragraph(ref t) => self.paragraph(t),
+            Token::PreFormat(ref t) => self.preformat(t),
+            Token::Span(ref t) => self.span(t),
+        }
+    }
+
+    fn render(&mut self, tokens: &[Token]) -> String {
+        tokens.iter().map(|token| self.token(token)).collect()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::syntax::content::{
+    render::Renderer,
+    parser::token::{
+        Token, anchor::Anchor, code::Code, header::Header,
+        linebreak::LineBreak, literal::Literal, paragraph::Paragraph,
+        preformat::PreFormat, span::Span,
+    },
+};
+
+/// Renders to Gemtext.
+///
+/// Gemtext links can't be inline, so anchors keep only their text in place
+/// and are listed as link lines after the block that contains them.
+#[derive(Default)]
+pub struct Gemtext {
+    links: Vec<(String, String)>,
+}
+
+impl Gemtext {
+    fn flush_links(&mut self) -> String {
+        self.links
+            .drain(..)
+            .map(|(href, text)| format!("\n=> {href} {text}"))
+            .collect()
+    }
+}
+
+impl Renderer for Gemtext {
+    fn anchor(&mut self, anchor: &Anchor) -> String {
+        let Some(href) = anchor.href() else {
+            panic!(
+                "Attempt to render anchor {anchor:?} without knowing its destination."
+            )
+        };
+
+        // A space would end the URL of a link line
+        let url = anchor
+            .node_id()
+            .map_or(href, |id| format!("/node/{}", encode(&id)));
+        self.links.push((url, anchor.text.clone()));
+        anchor.text.clone()
+    }
+
+    fn code(&mut self, _code: &Code) -> String {
+        String::new()
+    }
+
+    fn header(&mut self, header: &Header) -> String {
+        match header.open() {
+            Some(true) => "#".repeat(header.level().min(3).into()),
+            Some(false) => self.flush_links(),
+            None => panic!(
+                "Attempt to render a header tag while open state is unknown"
+            ),
+        }
+    }
+
+    fn linebreak(&mut self, _linebreak: &LineBreak) -> String {
+        "\n".to_owned()
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        literal.text()
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String {
+        match paragraph.open() {
+            Some(true) => String::new(),
+            Some(false) => self.flush_links(),
+            None => panic!(
+                "Attempt to render a paragraph tag while open state is unknown"
+            ),
+        }
+    }
+
+    fn preformat(&mut self, _preformat: &PreFormat) -> String {
+        String::from("```")
+    }
+
+    fn span(&mut self, _span: &Span) -> String {
+        String::new()
+    }
+
+    fn render(&mut self, tokens: &[Token]) -> String {
+        let mut text: String =
+            tokens.iter().map(|token| self.token(token)).collect();
+        text.push_str(&self.flush_links());
+        text
+    }
+}
+
+/// Percent-encodes a node ID for use in a Gemini URL, where a space would
+/// end the URL of a link line.
+pub fn encode(id: &str) -> String {
+    id.replace('%', "%25").replace(' ', "%20")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::content;
+
+    use super::*;
+
+    #[test]
+    fn links_after_paragraph() {
+        assert_eq!(
+            content::render(
+                "See |a node|Node and |https://a.b|.\nNext.",
+                &mut Gemtext::default()
+            ),
+            "See a node and https://a.b.\n=> /node/Node a node\n\
+            => https://a.b https://a.b\nNext."
+        );
+    }
+
+    #[test]
+    fn spaced_ids() {
+        let mut gemtext = Gemtext::default();
+        let anchor = Anchor {
+            text: String::from("the start"),
+            destination: Some(String::from("Big Bang")),
+            leading: false,
+        };
+        assert_eq!(gemtext.anchor(&anchor), "the start");
+        assert_eq!(gemtext.flush_links(), "\n=> /node/Big%20Bang the start");
+    }
+
+    #[test]
+    fn header_levels() {
+        assert_eq!(
+            content::render("#### Deep `code`\n", &mut Gemtext::default()),
+            "### Deep code\n"
+        );
+    }
+
+    #[test]
+    fn preformat() {
+        assert_eq!(
+            content::render("`\n|x|\n`\n", &mut Gemtext::default()),
+            "```\n|x|\n```\n"
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use crate::syntax::content::{
+    render::Renderer,
+    parser::token::{
+        anchor::Anchor, code::Code, header::Header, linebreak::LineBreak,
+        literal::Literal, paragraph::Paragraph, preformat::PreFormat,
+        span::Span,
+    },
+};
+
+pub struct Html;
+
+impl Renderer for Html {
+    fn anchor(&mut self, anchor: &Anchor) -> String {
+        let Some(href) = anchor.href() else {
+            panic!(
+                "Attempt to render anchor {anchor:?} without knowing its destination."
+            )
+        };
+
+        format!(r#"<a href="{href}">{}</a>"#, anchor.text)
+    }
+
+    fn code(&mut self, code: &Code) -> String {
+        if code.open() {
+            String::from("<code>")
+        } else {
+            String::from("</code>")
+        }
+    }
+
+    fn header(&mut self, header: &Header) -> String {
+        if let Some(open) = header.open() {
+            if open && let Some(ref id) = header.dom_id {
+                format!(r#"<h{} id="{}">"#, header.level(), id)
+            } else if open {
+                format!("<h{}>", header.level())
+            } else {
+                format!("</h{}>", header.level())
+            }
+        } else {
+            panic!("Attempt to render a header tag while open state is unknown")
+        }
+    }
+
+    fn linebreak(&mut self, _linebreak: &LineBreak) -> String {
+        "\n".to_owned()
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        literal.text()
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph) -> String {
+        if let Some(open) = paragraph.open() {
+            if open {
+                "<p>".to_owned()
+            } else {
+                "</p>".to_owned()
+            }
+        } else {
+            panic!(
+                "Attempt to render a paragraph tag while open state is unknown"
+            )
+        }
+    }
+
+    fn preformat(&mut self, preformat: &PreFormat) -> String {
+        if let Some(o) = preformat.open() {
+            if o {
+                "<pre>".to_owned()
+            } else {
+                "</pre>".to_owned()
+            }
+        } else {
+            panic!(
+                "Attempt to render a preformat tag while open state is unknown"
+            )
+        }
+    }
+
+    fn span(&mut self, span: &Span) -> String {
+        if let Some(open) = span.open() {
+            if open {
+                "<span>".to_owned()
+            } else {
+                "</span>".to_owned()
+            }
+        } else {
+            panic!("Attempt to render a span tag while open state is unknown")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::content::{Parseable as _, parser::lexeme::Lexeme};
+
+    use super::*;
+
+    #[test]
+    fn render_anchor() {
+        let anchor = Anchor::new("AnchorText", "AnchorDest", true);
+        assert_eq!(
+            Html.anchor(&anchor),
+            r#"<a href="/node/AnchorDest">AnchorText</a>"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "without knowing its destination")]
+    fn unknown_destination_render() {
+        let anchor = Anchor::empty();
+        drop(Html.anchor(&anchor));
+    }
+
+    #[test]
+    fn render_code() {
+        assert_eq!(Html.code(&Code::new(true)), "<code>");
+        assert_eq!(Html.code(&Code::new(false)), "</code>");
+    }
+
+    #[test]
+    fn no_id_header() {
+        let open_header = Header::from_u8(2, true, None);
+        let closed_header = Header::from_u8(2, false, None);
+        assert_eq!(Html.header(&open_header), "<h2>");
+        assert_eq!(Html.header(&closed_header), "</h2>");
+    }
+
+    #[test]
+    fn id_header() {
+        let header = Header::from_u8(3, true, Some("id"));
+        assert_eq!(Html.header(&header), r#"<h3 id="id">"#);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Attempt to render a paragraph tag while open state is unknown"
+    )]
+    fn paragraph_state_unknown() {
+        let p = Paragraph::lex(&Lexeme::new("", ""));
+        drop(Html.paragraph(&p));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Attempt to render a preformat tag while open state is unknown"
+    )]
+    fn preformat_state_unknown() {
+        let from_empty_lexeme = PreFormat::lex(&Lexeme::new("", ""));
+        drop(Html.preformat(&from_empty_lexeme));
+    }
+
+    #[test]
+    fn render_span() {
+        assert_eq!(Html.span(&Span::new(true)), "<span>");
+        assert_eq!(Html.span(&Span::new(false)), "</span>");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Attempt to render a span tag while open state is unknown"
+    )]
+    fn span_state_unknown() {
+        let open_span = Span::lex(&Lexeme::new("", ""));
+        drop(Html.span(&open_span));
+    }
+}
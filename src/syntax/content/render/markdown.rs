@@ -0,0 +1,120 @@
+use crate::syntax::content::{
+    render::Renderer,
+    parser::token::{
+        anchor::Anchor, code::Code, header::Header, linebreak::LineBreak,
+        literal::Literal, paragraph::Paragraph, preformat::PreFormat,
+        span::Span,
+    },
+};
+
+/// Renders to `CommonMark`.
+///
+/// With `wikilinks` set, node anchors become `[[WikiLinks]]` as used by
+/// Obsidian and Logseq instead of links under `/node/`.
+#[derive(Default)]
+pub struct Markdown {
+    pub wikilinks: bool,
+}
+
+impl Renderer for Markdown {
+    fn anchor(&mut self, anchor: &Anchor) -> String {
+        let Some(href) = anchor.href() else {
+            panic!(
+                "Attempt to render anchor {anchor:?} without knowing its destination."
+            )
+        };
+
+        match anchor.node_id().map(|id| file_stem(&id)) {
+            Some(stem) if self.wikilinks && stem == anchor.text => {
+                format!("[[{stem}]]")
+            },
+            Some(stem) if self.wikilinks => {
+                format!("[[{stem}|{}]]", anchor.text)
+            },
+            Some(_) | None => format!("[{}]({href})", anchor.text),
+        }
+    }
+
+    fn code(&mut self, _code: &Code) -> String {
+        String::from("`")
+    }
+
+    fn header(&mut self, header: &Header) -> String {
+        match header.open() {
+            Some(true) => "#".repeat(header.level().into()),
+            Some(false) => String::new(),
+            None => panic!(
+                "Attempt to render a header tag while open state is unknown"
+            ),
+        }
+    }
+
+    fn linebreak(&mut self, _linebreak: &LineBreak) -> String {
+        "\n".to_owned()
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        literal.text()
+    }
+
+    fn paragraph(&mut self, _paragraph: &Paragraph) -> String {
+        String::new()
+    }
+
+    fn preformat(&mut self, _preformat: &PreFormat) -> String {
+        String::from("```")
+    }
+
+    fn span(&mut self, _span: &Span) -> String {
+        String::new()
+    }
+}
+
+/// The name, without extension, of the file a node is written to when
+/// exporting to Markdown, which is what wikilinks to it point to. Slashes
+/// are replaced, since they'd make the name a path.
+pub fn file_stem(id: &str) -> String {
+    id.replace(['/', '\\'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::content;
+
+    use super::*;
+
+    #[test]
+    fn anchors() {
+        assert_eq!(
+            content::render(
+                "|a node|Node and |https://a.b|\n",
+                &mut Markdown::default()
+            ),
+            "[a node](/node/Node) and [https://a.b](https://a.b)\n"
+        );
+    }
+
+    #[test]
+    fn wikilinks() {
+        let mut markdown = Markdown { wikilinks: true };
+        assert_eq!(
+            content::render(
+                "|Node| and |a node|Node|, |https://a.b|\n",
+                &mut markdown
+            ),
+            "[[Node]] and [[Node|a node]], [https://a.b](https://a.b)\n"
+        );
+        assert_eq!(
+            content::render("|Up\\Down|\n", &mut markdown),
+            "[[Up-Down|Up\\Down]]\n"
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(
+            content::render("### H\n`\nx\n`\n`y`\n", &mut Markdown::default()),
+            "### H\n```\nx\n```\n`y`\n"
+        );
+    }
+}
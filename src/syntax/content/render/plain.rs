@@ -0,0 +1,63 @@
+use crate::syntax::content::{
+    render::Renderer,
+    parser::token::{
+        anchor::Anchor, code::Code, header::Header, linebreak::LineBreak,
+        literal::Literal, paragraph::Paragraph, preformat::PreFormat,
+        span::Span,
+    },
+};
+
+/// Renders only the readable text: anchors keep their text, markup is dropped.
+pub struct Plain;
+
+impl Renderer for Plain {
+    fn anchor(&mut self, anchor: &Anchor) -> String {
+        anchor.text.clone()
+    }
+
+    fn code(&mut self, _code: &Code) -> String {
+        String::new()
+    }
+
+    fn header(&mut self, _header: &Header) -> String {
+        String::new()
+    }
+
+    fn linebreak(&mut self, _linebreak: &LineBreak) -> String {
+        "\n".to_owned()
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        literal.text()
+    }
+
+    fn paragraph(&mut self, _paragraph: &Paragraph) -> String {
+        String::new()
+    }
+
+    fn preformat(&mut self, _preformat: &PreFormat) -> String {
+        String::new()
+    }
+
+    fn span(&mut self, _span: &Span) -> String {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::content;
+
+    use super::*;
+
+    #[test]
+    fn anchor_text() {
+        let anchor = Anchor::new("AnchorText", "AnchorDest", true);
+        assert_eq!(Plain.anchor(&anchor), "AnchorText");
+    }
+
+    #[test]
+    fn drops_markup() {
+        assert_eq!(content::render("## A `b`\n", &mut Plain), " A b\n");
+    }
+}
@@ -0,0 +1,694 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io,
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    syntax::serial::migrate,
+    types::{Graph, Node},
+};
+
+// Words of unchanged text shown around each change
+const CONTEXT: usize = 4;
+
+// Comparing word by word takes a table of old by new changed words, so
+// texts needing a larger one are only reported as changed
+const MAX_TABLE: usize = 4_000_000;
+
+/// The differences between two versions of a graph.
+#[derive(Serialize, Default, PartialEq, Eq, Debug)]
+pub struct Report {
+    pub added_nodes: Vec<String>,
+    pub removed_nodes: Vec<String>,
+    pub renamed_nodes: Vec<Rename>,
+    pub titles: Vec<TitleChange>,
+    pub texts: Vec<TextChange>,
+    pub added_edges: Vec<EdgeKey>,
+    pub removed_edges: Vec<EdgeKey>,
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TitleChange {
+    pub id: String,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TextChange {
+    pub id: String,
+    /// Empty when the texts differ too much to compare word by word.
+    pub words: Vec<Words>,
+}
+
+/// A run of words that were kept, added or removed.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Words {
+    Kept(String),
+    Added(String),
+    Removed(String),
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct EdgeKey {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        *self == Report::default()
+    }
+
+    /// Lists the differences for reading in a terminal, with changed words
+    /// marked as `[-removed-]` and `{+added+}`.
+    pub fn make_text(&self) -> String {
+        if self.is_empty() {
+            return String::from("No differences.\n");
+        }
+
+        let sections: [(&str, Vec<String>); 7] = [
+            (
+                "Added nodes",
+                self.added_nodes
+                    .iter()
+                    .map(|id| format!("+ {id}"))
+                    .collect(),
+            ),
+            (
+                "Removed nodes",
+                self.removed_nodes
+                    .iter()
+                    .map(|id| format!("- {id}"))
+                    .collect(),
+            ),
+            (
+                "Renamed nodes",
+                self.renamed_nodes
+                    .iter()
+                    .map(|rename| format!("~ {} -> {}", rename.from, rename.to))
+                    .collect(),
+            ),
+            (
+                "Changed titles",
+                self.titles
+                    .iter()
+                    .map(|change| {
+                        format!(
+                            "~ {}: {:?} -> {:?}",
+                            change.id, change.old, change.new
+                        )
+                    })
+                    .collect(),
+            ),
+            (
+                "Changed text",
+                self.texts
+                    .iter()
+                    .map(|change| {
+                        if change.words.is_empty() {
+                            format!("~ {}: text changed", change.id)
+                        } else {
+                            format!(
+                                "~ {}: {}",
+                                change.id,
+                                mark_words(&change.words)
+                            )
+                        }
+                    })
+                    .collect(),
+            ),
+            (
+                "Added edges",
+                self.added_edges
+                    .iter()
+                    .map(|edge| format!("+ {}", describe_edge(edge)))
+                    .collect(),
+            ),
+            (
+                "Removed edges",
+                self.removed_edges
+                    .iter()
+                    .map(|edge| format!("- {}", describe_edge(edge)))
+                    .collect(),
+            ),
+        ];
+
+        let mut out = String::new();
+        for (heading, lines) in sections {
+            if lines.is_empty() {
+                continue;
+            }
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(heading);
+            out.push_str(":\n");
+            for line in lines {
+                out.push_str("  ");
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// Reads the graph TOML at `path` as written, without derived data, but
+/// upgraded to the current format version, so that graphs written in
+/// different versions are only compared by what they hold.
+///
+/// # Errors
+/// Fails if the file can't be read or isn't a valid graph.
+pub fn read(path: &Path) -> io::Result<Graph> {
+    let source = std::fs::read_to_string(path)?;
+    let graph = migrate::load(&source);
+    if graph.meta.messages.is_empty() {
+        Ok(graph)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Failed to parse {}: {}",
+                path.display(),
+                graph.meta.messages.join("; ")
+            ),
+        ))
+    }
+}
+
+/// Compares two graphs as written by their authors. A removed node and an
+/// added node with the same text are reported as a rename, and edges are
+/// compared after renames so they only show up when they actually changed.
+/// Text is compared word by word, ignoring changes in whitespace.
+pub fn compare(old: &Graph, new: &Graph) -> Report {
+    let mut removed: Vec<&String> = old
+        .nodes
+        .keys()
+        .filter(|id| !new.nodes.contains_key(*id))
+        .collect();
+    let mut added: Vec<&String> = new
+        .nodes
+        .keys()
+        .filter(|id| !old.nodes.contains_key(*id))
+        .collect();
+    removed.sort();
+    added.sort();
+
+    let mut renames: BTreeMap<&str, &str> = BTreeMap::new();
+    for from in &removed {
+        let Some(old_node) = old.nodes.get(*from) else {
+            continue;
+        };
+        let text = words(&old_node.text);
+        if text.is_empty() {
+            continue;
+        }
+
+        let paired = added.iter().find(|to| {
+            !renames.values().any(|taken| taken == *to)
+                && new.nodes.get(**to).is_some_and(|n| words(&n.text) == text)
+        });
+        if let Some(to) = paired {
+            renames.insert(from.as_str(), to.as_str());
+        }
+    }
+
+    // Nodes present in both graphs, under their new IDs when renamed
+    let mut kept: Vec<(&str, &str)> = old
+        .nodes
+        .keys()
+        .filter(|id| new.nodes.contains_key(*id))
+        .map(|id| (id.as_str(), id.as_str()))
+        .chain(renames.iter().map(|(from, to)| (*from, *to)))
+        .collect();
+    kept.sort_by_key(|&(_, to)| to);
+
+    let mut titles = vec![];
+    let mut texts = vec![];
+    for (from, to) in kept {
+        let (Some(old_node), Some(new_node)) =
+            (old.nodes.get(from), new.nodes.get(to))
+        else {
+            continue;
+        };
+
+        if old_node.title != new_node.title {
+            titles.push(TitleChange {
+                id: to.to_owned(),
+                old: old_node.title.clone(),
+                new: new_node.title.clone(),
+            });
+        }
+
+        let (old_words, new_words) =
+            (words(&old_node.text), words(&new_node.text));
+        if old_words != new_words {
+            texts.push(TextChange {
+                id: to.to_owned(),
+                words: compare_words(&old_words, &new_words),
+            });
+        }
+    }
+
+    let rename = |id: &str| -> String {
+        renames.get(id).copied().unwrap_or(id).to_owned()
+    };
+    let old_edges: BTreeSet<EdgeKey> = make_edges(&old.nodes)
+        .into_iter()
+        .map(|edge| EdgeKey {
+            from: rename(&edge.from),
+            to: rename(&edge.to),
+            kind: edge.kind,
+        })
+        .collect();
+    let new_edges = make_edges(&new.nodes);
+
+    Report {
+        added_nodes: added
+            .into_iter()
+            .filter(|id| !renames.values().any(|to| to == id))
+            .cloned()
+            .collect(),
+        removed_nodes: removed
+            .into_iter()
+            .filter(|id| !renames.contains_key(id.as_str()))
+            .cloned()
+            .collect(),
+        renamed_nodes: renames
+            .iter()
+            .map(|(from, to)| Rename {
+                from: (*from).to_owned(),
+                to: (*to).to_owned(),
+            })
+            .collect(),
+        titles,
+        texts,
+        added_edges: new_edges.difference(&old_edges).cloned().collect(),
+        removed_edges: old_edges.difference(&new_edges).cloned().collect(),
+    }
+}
+
+// Edges as written, both from `connections` and from `links`
+fn make_edges(nodes: &HashMap<String, Node>) -> BTreeSet<EdgeKey> {
+    let mut edges = BTreeSet::new();
+
+    for (id, node) in nodes {
+        for edge in node.connections.iter().flatten() {
+            edges.insert(EdgeKey {
+                from: if edge.from.is_empty() {
+                    id.clone()
+                } else {
+                    edge.from.clone()
+                },
+                to: edge.to.clone(),
+                kind: edge.kind.clone(),
+            });
+        }
+        for link in &node.links {
+            edges.insert(EdgeKey {
+                from: id.clone(),
+                to: link.clone(),
+                kind: String::new(),
+            });
+        }
+    }
+
+    edges
+}
+
+fn words(text: &str) -> Vec<&str> {
+    text.split_whitespace().collect()
+}
+
+// Aligns the words along their longest common subsequence, grouping each
+// stretch of changes into removed words followed by added words
+fn compare_words(old: &[&str], new: &[&str]) -> Vec<Words> {
+    let prefix = old.iter().zip(new).take_while(|&(a, b)| a == b).count();
+    let old_rest = old.get(prefix..).unwrap_or_default();
+    let new_rest = new.get(prefix..).unwrap_or_default();
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let old_middle = old_rest
+        .get(..old_rest.len().saturating_sub(suffix))
+        .unwrap_or_default();
+    let new_middle = new_rest
+        .get(..new_rest.len().saturating_sub(suffix))
+        .unwrap_or_default();
+
+    if old_middle.len().saturating_mul(new_middle.len()) > MAX_TABLE {
+        return vec![];
+    }
+
+    // lengths[i * width + j] is the LCS length of old_middle[i..] and
+    // new_middle[j..]
+    let width = new_middle.len().saturating_add(1);
+    let mut lengths =
+        vec![0_usize; old_middle.len().saturating_add(1).saturating_mul(width)];
+    let at = |i: usize, j: usize| i.saturating_mul(width).saturating_add(j);
+
+    for (i, old_word) in old_middle.iter().enumerate().rev() {
+        for (j, new_word) in new_middle.iter().enumerate().rev() {
+            let length = if old_word == new_word {
+                lengths
+                    .get(at(i.saturating_add(1), j.saturating_add(1)))
+                    .copied()
+                    .unwrap_or_default()
+                    .saturating_add(1)
+            } else {
+                let below = lengths.get(at(i.saturating_add(1), j));
+                let right = lengths.get(at(i, j.saturating_add(1)));
+                below.max(right).copied().unwrap_or_default()
+            };
+            if let Some(cell) = lengths.get_mut(at(i, j)) {
+                *cell = length;
+            }
+        }
+    }
+
+    let mut runs = Runs::default();
+    runs.keep(old.get(..prefix).unwrap_or_default());
+
+    let (mut i, mut j) = (0, 0);
+    while let (Some(old_word), Some(new_word)) =
+        (old_middle.get(i), new_middle.get(j))
+    {
+        if old_word == new_word {
+            runs.keep(&[old_word]);
+            i = i.saturating_add(1);
+            j = j.saturating_add(1);
+        } else if lengths.get(at(i.saturating_add(1), j))
+            >= lengths.get(at(i, j.saturating_add(1)))
+        {
+            runs.removed.push(old_word);
+            i = i.saturating_add(1);
+        } else {
+            runs.added.push(new_word);
+            j = j.saturating_add(1);
+        }
+    }
+    runs.removed
+        .extend(old_middle.get(i..).unwrap_or_default().iter().copied());
+    runs.added
+        .extend(new_middle.get(j..).unwrap_or_default().iter().copied());
+
+    runs.keep(
+        old_rest
+            .get(old_rest.len().saturating_sub(suffix)..)
+            .unwrap_or_default(),
+    );
+    runs.finish()
+}
+
+#[derive(Default)]
+struct Runs<'a> {
+    done: Vec<Words>,
+    kept: Vec<&'a str>,
+    removed: Vec<&'a str>,
+    added: Vec<&'a str>,
+}
+
+impl<'a> Runs<'a> {
+    fn keep(&mut self, words: &[&'a str]) {
+        if words.is_empty() {
+            return;
+        }
+        self.flush_changes();
+        self.kept.extend_from_slice(words);
+    }
+
+    fn flush_changes(&mut self) {
+        if self.removed.is_empty() && self.added.is_empty() {
+            return;
+        }
+        if !self.kept.is_empty() {
+            self.done.push(Words::Kept(self.kept.join(" ")));
+            self.kept.clear();
+        }
+        if !self.removed.is_empty() {
+            self.done.push(Words::Removed(self.removed.join(" ")));
+            self.removed.clear();
+        }
+        if !self.added.is_empty() {
+            self.done.push(Words::Added(self.added.join(" ")));
+            self.added.clear();
+        }
+    }
+
+    fn finish(mut self) -> Vec<Words> {
+        self.flush_changes();
+        if !self.kept.is_empty() {
+            self.done.push(Words::Kept(self.kept.join(" ")));
+        }
+        self.done
+    }
+}
+
+// Long unchanged runs are shortened to the words next to the changes
+fn mark_words(runs: &[Words]) -> String {
+    let last = runs.len().saturating_sub(1);
+
+    runs.iter()
+        .enumerate()
+        .map(|(index, run)| match *run {
+            Words::Added(ref text) => format!("{{+{text}+}}"),
+            Words::Removed(ref text) => format!("[-{text}-]"),
+            Words::Kept(ref text) => {
+                let kept: Vec<&str> = text.split(' ').collect();
+                let head = kept.get(..CONTEXT).unwrap_or(&kept).join(" ");
+                let tail = kept
+                    .get(kept.len().saturating_sub(CONTEXT)..)
+                    .unwrap_or(&kept)
+                    .join(" ");
+
+                if kept.len() <= CONTEXT.saturating_mul(2) {
+                    text.clone()
+                } else if index == 0 {
+                    format!("… {tail}")
+                } else if index == last {
+                    format!("{head} …")
+                } else {
+                    format!("{head} … {tail}")
+                }
+            },
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn describe_edge(edge: &EdgeKey) -> String {
+    if edge.kind.is_empty() {
+        format!("{} -> {}", edge.from, edge.to)
+    } else {
+        format!("{} -> {} ({})", edge.from, edge.to, edge.kind)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_graph(source: &str) -> Graph {
+        toml::from_str(&format!("root_node = \"A\"\n{source}")).unwrap()
+    }
+
+    #[test]
+    fn word_changes() {
+        let old = words("the quick brown fox jumps over the dog");
+        let new = words("the slow brown fox leaps over the lazy dog");
+
+        assert_eq!(
+            compare_words(&old, &new),
+            [
+                Words::Kept(String::from("the")),
+                Words::Removed(String::from("quick")),
+                Words::Added(String::from("slow")),
+                Words::Kept(String::from("brown fox")),
+                Words::Removed(String::from("jumps")),
+                Words::Added(String::from("leaps")),
+                Words::Kept(String::from("over the")),
+                Words::Added(String::from("lazy")),
+                Words::Kept(String::from("dog")),
+            ]
+        );
+        assert_eq!(
+            compare_words(&[], &words("new text")),
+            [Words::Added(String::from("new text"))]
+        );
+    }
+
+    #[test]
+    fn long_texts() {
+        let make_text = |prefix: &str| {
+            (0..3000)
+                .map(|n| format!("{prefix}{n}"))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let old = make_graph(&format!(
+            "[nodes.A]\ntext = \"Start {}\"",
+            make_text("old")
+        ));
+        let new = make_graph(&format!(
+            "[nodes.A]\ntext = \"Start {}\"",
+            make_text("new")
+        ));
+
+        let report = compare(&old, &new);
+        assert!(report.texts.first().unwrap().words.is_empty());
+        assert!(report.make_text().contains("~ A: text changed"));
+    }
+
+    #[test]
+    fn marked_context() {
+        let old = words("one two three four five six seven eight nine ten");
+        let new = words("one two three four five six seven eight nine 10");
+
+        assert_eq!(
+            mark_words(&compare_words(&old, &new)),
+            "… six seven eight nine [-ten-] {+10+}"
+        );
+    }
+
+    #[test]
+    fn nodes_and_edges() {
+        let old = make_graph(
+            r#"
+            [nodes.A]
+            text = "Points to B."
+            links = ["B"]
+
+            [nodes.B]
+            title = "Bee"
+            text = "Is pointed to."
+
+            [nodes.C]
+            text = "Goes away."
+            "#,
+        );
+        let new = make_graph(
+            r#"
+            [nodes.A]
+            text = """Points  to
+            the renamed B."""
+
+            [[nodes.A.connections]]
+            to = "Renamed"
+            kind = "points"
+
+            [nodes.Renamed]
+            title = "B"
+            text = "Is pointed to."
+
+            [nodes.D]
+            text = "Arrives."
+            "#,
+        );
+
+        let report = compare(&old, &new);
+        assert_eq!(report.added_nodes, ["D"]);
+        assert_eq!(report.removed_nodes, ["C"]);
+        assert_eq!(
+            report.renamed_nodes,
+            [Rename {
+                from: String::from("B"),
+                to: String::from("Renamed"),
+            }]
+        );
+        assert_eq!(
+            report.titles,
+            [TitleChange {
+                id: String::from("Renamed"),
+                old: String::from("Bee"),
+                new: String::from("B"),
+            }]
+        );
+        assert_eq!(report.texts.len(), 1);
+        assert_eq!(
+            mark_words(&report.texts.first().unwrap().words),
+            "Points to {+the renamed+} B."
+        );
+        assert_eq!(
+            report.added_edges,
+            [EdgeKey {
+                from: String::from("A"),
+                to: String::from("Renamed"),
+                kind: String::from("points"),
+            }]
+        );
+        assert_eq!(
+            report.removed_edges,
+            [EdgeKey {
+                from: String::from("A"),
+                to: String::from("Renamed"),
+                kind: String::new(),
+            }]
+        );
+
+        assert_eq!(
+            report.make_text(),
+            "Added nodes:\n  + D\n\n\
+            Removed nodes:\n  - C\n\n\
+            Renamed nodes:\n  ~ B -> Renamed\n\n\
+            Changed titles:\n  ~ Renamed: \"Bee\" -> \"B\"\n\n\
+            Changed text:\n  ~ A: Points to {+the renamed+} B.\n\n\
+            Added edges:\n  + A -> Renamed (points)\n\n\
+            Removed edges:\n  - A -> Renamed\n"
+        );
+    }
+
+    #[test]
+    fn unchanged() {
+        let graph = make_graph("[nodes.A]\ntext = \"Same.\"");
+        let report = compare(&graph, &graph);
+        assert!(report.is_empty());
+        assert_eq!(report.make_text(), "No differences.\n");
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json.pointer("/added_nodes").unwrap(),
+            &serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn unreadable() {
+        let missing =
+            read(Path::new("nonexistent-e3Rk0vQp.toml")).err().unwrap();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+
+        let path = std::env::temp_dir()
+            .join(format!("en-diff-invalid-{}.toml", std::process::id()));
+        std::fs::write(&path, "nodes = [").unwrap();
+        let invalid = read(&path).err().unwrap();
+        assert_eq!(invalid.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn older_versions() {
+        let path = std::env::temp_dir()
+            .join(format!("en-diff-unversioned-{}.toml", std::process::id()));
+        std::fs::write(&path, "root_node = \"A\"\n[nodes.A]\ntext = \"\"\n")
+            .unwrap();
+        let unversioned = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(unversioned.meta.version, migrate::CURRENT);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::types::Graph;
+
+pub mod csv;
+pub mod markdown;
+
+/// Writes `graph` under `path` in an external format, returning how many
+/// nodes were written.
+///
+/// # Errors
+/// Fails if the format is unknown or if writing to `path` fails.
+pub fn export(format: &str, graph: &Graph, path: &Path) -> io::Result<usize> {
+    match format {
+        "markdown" => markdown::export(graph, path),
+        "csv" | "tsv" => csv::export(graph, path, format),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown export format {format}"),
+        )),
+    }
+}
+
+/// Reads files in an external format into a graph TOML table, along with
+/// notes on anything that couldn't be converted.
+///
+/// # Errors
+/// Fails if the format is unknown, if it doesn't take the given number of
+/// paths or if reading them fails.
+pub fn import(
+    format: &str,
+    paths: &[PathBuf],
+) -> io::Result<(toml::Table, Vec<String>)> {
+    match (format, paths.first()) {
+        ("markdown", Some(directory)) if paths.len() == 1 => {
+            markdown::import(directory)
+        },
+        ("csv", Some(nodes)) if paths.len() <= 2 => {
+            let edges = paths.get(1).map(PathBuf::as_path);
+            Ok(csv::make_source(&csv::import(nodes, edges)?))
+        },
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown import format {format} for {} paths", paths.len()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_format() {
+        let error = export("9eXc1WQz", &Graph::new(None), Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn wrong_path_count() {
+        let error = import("markdown", &[]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
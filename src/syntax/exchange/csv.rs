@@ -0,0 +1,378 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    syntax::serial::modulate_graph,
+    types::{Edge, Graph, Node},
+};
+
+#[derive(Serialize, Deserialize)]
+struct NodeRow {
+    id: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default, deserialize_with = "read_flag")]
+    hidden: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EdgeRow {
+    from: String,
+    to: String,
+    #[serde(default)]
+    anchor: String,
+    #[serde(default)]
+    kind: String,
+}
+
+/// Reads a node table with `id`, `title`, `text` and `hidden` columns and an
+/// optional edge list with `from`, `to`, `anchor` and `kind` columns into a
+/// graph. Files ending in `.tsv` are read as tab-separated. The first node
+/// becomes the root.
+///
+/// # Errors
+/// Fails if a file can't be read or parsed, if a node ID is missing or
+/// repeated, or if an edge starts at a node that doesn't exist.
+pub fn import(
+    nodes_path: &Path,
+    edges_path: Option<&Path>,
+) -> io::Result<Graph> {
+    let mut nodes: HashMap<String, Node> = HashMap::new();
+    let mut root_node = String::new();
+
+    for result in make_reader(nodes_path)?.deserialize::<NodeRow>() {
+        let row = result.map_err(|e| invalid(nodes_path, &e.to_string()))?;
+
+        if row.id.is_empty() {
+            return Err(invalid(nodes_path, "node without an ID"));
+        }
+
+        if nodes.contains_key(&row.id) {
+            return Err(invalid(
+                nodes_path,
+                &format!("repeated ID {}", row.id),
+            ));
+        }
+
+        if root_node.is_empty() {
+            root_node.clone_from(&row.id);
+        }
+
+        nodes.insert(
+            row.id.clone(),
+            Node {
+                text: row.text,
+                title: row.title,
+                id: row.id,
+                hidden: row.hidden,
+                connections: Some(vec![]),
+                ..Node::default()
+            },
+        );
+    }
+
+    if let Some(path) = edges_path {
+        for result in make_reader(path)?.deserialize::<EdgeRow>() {
+            let row = result.map_err(|e| invalid(path, &e.to_string()))?;
+
+            let Some(connections) = nodes
+                .get_mut(&row.from)
+                .and_then(|node| node.connections.as_mut())
+            else {
+                return Err(invalid(
+                    path,
+                    &format!("edge from unknown node {:?}", row.from),
+                ));
+            };
+
+            connections.push(Edge {
+                to: row.to,
+                anchor: row.anchor,
+                from: row.from,
+                kind: row.kind,
+                detached: false,
+            });
+        }
+    }
+
+    Ok(modulate_graph(Graph {
+        nodes,
+        root_node,
+        ..Graph::new(None)
+    }))
+}
+
+/// Builds the graph TOML for an imported graph, along with a note for each
+/// detached edge.
+pub fn make_source(graph: &Graph) -> (toml::Table, Vec<String>) {
+    let mut nodes = toml::Table::new();
+    let mut report = vec![];
+
+    for node in sorted_nodes(graph) {
+        let mut table = toml::Table::new();
+        table.insert(String::from("text"), node.text.clone().into());
+
+        if node.title != node.id {
+            table.insert(String::from("title"), node.title.clone().into());
+        }
+
+        if node.hidden {
+            table.insert(String::from("hidden"), true.into());
+        }
+
+        let mut connections: Vec<toml::Value> = vec![];
+        for edge in node.connections.iter().flatten() {
+            if edge.detached {
+                report.push(format!(
+                    "Edge from {} to {} is detached",
+                    edge.from, edge.to
+                ));
+            }
+
+            let mut connection = toml::Table::new();
+            connection.insert(String::from("to"), edge.to.clone().into());
+            if !edge.anchor.is_empty() {
+                connection
+                    .insert(String::from("anchor"), edge.anchor.clone().into());
+            }
+            if !edge.kind.is_empty() {
+                connection
+                    .insert(String::from("kind"), edge.kind.clone().into());
+            }
+            connections.push(connection.into());
+        }
+
+        if !connections.is_empty() {
+            table.insert(String::from("connections"), connections.into());
+        }
+
+        nodes.insert(node.id.clone(), table.into());
+    }
+
+    let mut source = toml::Table::new();
+    source.insert(String::from("root_node"), graph.root_node.clone().into());
+    source.insert(String::from("nodes"), nodes.into());
+
+    (source, report)
+}
+
+/// Writes `nodes` and `edges` tables under `directory`, with `extension`
+/// being either `csv` or `tsv`, returning how many nodes were written.
+///
+/// # Errors
+/// Fails if the directory or either table can't be written.
+pub fn export(
+    graph: &Graph,
+    directory: &Path,
+    extension: &str,
+) -> io::Result<usize> {
+    fs::create_dir_all(directory)?;
+
+    let nodes = sorted_nodes(graph);
+    let mut node_writer =
+        make_writer(&directory.join(format!("nodes.{extension}")))?;
+    let mut edge_writer =
+        make_writer(&directory.join(format!("edges.{extension}")))?;
+
+    for node in &nodes {
+        node_writer.serialize(NodeRow {
+            id: node.id.clone(),
+            title: node.title.clone(),
+            text: node.text.clone(),
+            hidden: node.hidden,
+        })?;
+
+        for edge in node.connections.iter().flatten() {
+            edge_writer.serialize(EdgeRow {
+                from: node.id.clone(),
+                to: edge.to.clone(),
+                anchor: edge.anchor.clone(),
+                kind: edge.kind.clone(),
+            })?;
+        }
+    }
+
+    node_writer.flush()?;
+    edge_writer.flush()?;
+
+    Ok(nodes.len())
+}
+
+// The root comes first so it stays the root when the tables are imported
+fn sorted_nodes(graph: &Graph) -> Vec<&Node> {
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by(|a, b| {
+        (a.id != graph.root_node, &a.id).cmp(&(b.id != graph.root_node, &b.id))
+    });
+    nodes
+}
+
+fn delimiter(path: &Path) -> u8 {
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("tsv"))
+    {
+        b'\t'
+    } else {
+        b','
+    }
+}
+
+fn make_reader(path: &Path) -> io::Result<csv::Reader<fs::File>> {
+    Ok(csv::ReaderBuilder::new()
+        .delimiter(delimiter(path))
+        .trim(csv::Trim::Headers)
+        .from_reader(fs::File::open(path)?))
+}
+
+fn make_writer(path: &Path) -> io::Result<csv::Writer<fs::File>> {
+    Ok(csv::WriterBuilder::new()
+        .delimiter(delimiter(path))
+        .from_writer(fs::File::create(path)?))
+}
+
+fn invalid(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {message}", path.display()),
+    )
+}
+
+// Spreadsheets write booleans in many ways, so accept the common ones
+fn read_flag<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<bool, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    Ok(matches!(
+        raw.trim().to_lowercase().as_str(),
+        "true" | "yes" | "y" | "x" | "1"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_directory(name: &str) -> std::path::PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("en-csv-{name}-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn import_tables() {
+        let directory = make_directory("import");
+        let nodes = directory.join("nodes.csv");
+        let edges = directory.join("edges.tsv");
+        fs::write(
+            &nodes,
+            "id, title, text, hidden\nQuark,,\"Forms\nhadrons.\",\n\
+            Hadron,Hadrons,Made of quarks.,TRUE\n",
+        )
+        .unwrap();
+        fs::write(
+            &edges,
+            "from\tto\tanchor\tkind\nQuark\tHadron\tforms\tpart_of\n\
+            Hadron\tGluon\t\t\n",
+        )
+        .unwrap();
+
+        let graph = import(&nodes, Some(&edges)).unwrap();
+        let quark = graph.nodes.get("Quark").unwrap();
+        let hadron = graph.nodes.get("Hadron").unwrap();
+
+        assert_eq!(graph.root_node, "Quark");
+        assert_eq!(quark.title, "Quark");
+        assert_eq!(quark.text, "Forms\nhadrons.");
+        assert!(!quark.hidden);
+        assert!(hadron.hidden);
+
+        let edge = quark.connections.as_ref().unwrap().first().unwrap();
+        assert_eq!(edge.kind, "part_of");
+        assert_eq!(edge.anchor, "forms");
+        assert!(!edge.detached);
+
+        let detached = hadron.connections.as_ref().unwrap().first().unwrap();
+        assert!(detached.detached);
+        assert_eq!(graph.incoming.get("Hadron").unwrap().len(), 1);
+
+        let (_, report) = make_source(&graph);
+        assert_eq!(report, ["Edge from Hadron to Gluon is detached"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn import_errors() {
+        let directory = make_directory("errors");
+        let nodes = directory.join("nodes.csv");
+        let edges = directory.join("edges.csv");
+        fs::write(&edges, "from,to\nNowhere,Quark\n").unwrap();
+
+        fs::write(&nodes, "id,title\nQuark,\nQuark,\n").unwrap();
+        let repeated = import(&nodes, None).err().unwrap();
+        assert!(repeated.to_string().contains("repeated ID Quark"));
+
+        fs::write(&nodes, "id,title\nQuark,\n").unwrap();
+        let unknown = import(&nodes, Some(&edges)).err().unwrap();
+        assert!(unknown.to_string().contains("unknown node \"Nowhere\""));
+
+        fs::write(&nodes, "title\nQuark\n").unwrap();
+        let missing = import(&nodes, None).err().unwrap();
+        assert_eq!(missing.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let directory = make_directory("round-trip");
+        let original = modulate_graph(Graph {
+            root_node: String::from("Quark"),
+            nodes: HashMap::from([
+                (
+                    String::from("Quark"),
+                    Node {
+                        text: String::from(
+                            "Forms |hadrons|Hadron|, \"quoted\".",
+                        ),
+                        links: vec![String::from("Gluon")],
+                        ..Node::default()
+                    },
+                ),
+                (
+                    String::from("Hadron"),
+                    Node {
+                        text: String::from("Made of quarks."),
+                        title: String::from("Hadrons"),
+                        hidden: true,
+                        ..Node::default()
+                    },
+                ),
+            ]),
+            ..Graph::new(None)
+        });
+
+        assert_eq!(export(&original, &directory, "tsv").unwrap(), 2);
+        let imported = import(
+            &directory.join("nodes.tsv"),
+            Some(&directory.join("edges.tsv")),
+        )
+        .unwrap();
+
+        assert_eq!(imported.root_node, "Quark");
+        for (id, node) in &original.nodes {
+            let copy = imported.nodes.get(id).unwrap();
+            assert_eq!(copy.text, node.text);
+            assert_eq!(copy.title, node.title);
+            assert_eq!(copy.hidden, node.hidden);
+            assert_eq!(copy.connections, node.connections);
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    syntax::content::{
+        self,
+        render::markdown::{file_stem, Markdown},
+    },
+    types::{Graph, Node},
+};
+
+/// Writes one Markdown file per node, named after its ID, with the node's
+/// fields as YAML front matter and anchors to nodes as `[[WikiLinks]]`.
+///
+/// # Errors
+/// Fails if two IDs would be written to the same file, such as `a/b` and
+/// `a-b`, or if the directory or any of the files can't be written.
+pub fn export(graph: &Graph, directory: &Path) -> io::Result<usize> {
+    let mut nodes: Vec<&Node> = graph.nodes.values().collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut stems: HashMap<String, &str> = HashMap::new();
+    for node in &nodes {
+        let stem = file_stem(&node.id);
+        if let Some(taken) = stems.insert(stem.clone(), &node.id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Nodes {taken} and {} would both be written to {stem}.md, \
+                        rename one of them before exporting",
+                    node.id
+                ),
+            ));
+        }
+    }
+
+    fs::create_dir_all(directory)?;
+
+    for node in &nodes {
+        let path = directory.join(format!("{}.md", file_stem(&node.id)));
+        fs::write(path, make_document(node))?;
+    }
+
+    Ok(nodes.len())
+}
+
+fn make_document(node: &Node) -> String {
+    let aliases = if node.title == node.id {
+        vec![]
+    } else {
+        vec![quote(&node.title)]
+    };
+
+    let links: Vec<String> = node
+        .links
+        .iter()
+        .map(|link| quote(&wikilink(link)))
+        .collect();
+
+    // Edges from `links` are already listed above
+    let connections: Vec<String> = node
+        .connections
+        .iter()
+        .flatten()
+        .filter(|edge| {
+            !(edge.anchor.is_empty() && node.links.contains(&edge.to))
+        })
+        .map(|edge| {
+            format!(
+                "  - to: {}\n    anchor: {}",
+                quote(&wikilink(&edge.to)),
+                quote(&edge.anchor)
+            )
+        })
+        .collect();
+
+    let mut front_matter = vec![
+        String::from("---"),
+        format!("title: {}", quote(&node.title)),
+        format!("id: {}", quote(&node.id)),
+        format!("hidden: {}", node.hidden),
+        format!("aliases: [{}]", aliases.join(", ")),
+        format!("links: [{}]", links.join(", ")),
+    ];
+
+    if !node.summary.is_empty() {
+        front_matter.push(format!("summary: {}", quote(&node.summary)));
+    }
+
+    if connections.is_empty() {
+        front_matter.push(String::from("connections: []"));
+    } else {
+        front_matter.push(String::from("connections:"));
+        front_matter.extend(connections);
+    }
+
+    front_matter.push(String::from("---"));
+
+    let body = content::render(&node.text, &mut Markdown { wikilinks: true });
+
+    format!("{}\n\n{}\n", front_matter.join("\n"), body.trim())
+}
+
+/// Reads a folder of Markdown files into a graph TOML table, one node per
+/// file, along with notes on anything that couldn't be converted.
+///
+/// # Errors
+/// Fails if the directory or any of its files can't be read.
+pub fn import(directory: &Path) -> io::Result<(toml::Table, Vec<String>)> {
+    let mut files = vec![];
+    collect_files(directory, &mut files)?;
+    files.sort();
+
+    let mut nodes = toml::Table::new();
+    let mut report = vec![];
+
+    for file in files {
+        let stem = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let id = make_id(&stem);
+        let name = file.display().to_string();
+
+        if nodes.contains_key(&id) {
+            report.push(format!("{name}: skipped, ID {id} is already taken"));
+            continue;
+        }
+
+        if id != stem {
+            report.push(format!("{name}: ID changed from {stem:?} to {id}"));
+        }
+
+        let source = fs::read_to_string(&file)?;
+        let node = convert(&id, &source, &mut |note| {
+            report.push(format!("{name}:{note}"));
+        });
+        nodes.insert(id, toml::Value::Table(node));
+    }
+
+    let root = ["index", "Index", "README", "readme"]
+        .iter()
+        .find(|id| nodes.contains_key(**id))
+        .map(|id| (*id).to_owned())
+        .or_else(|| nodes.keys().next().cloned())
+        .unwrap_or_default();
+
+    let mut graph = toml::Table::new();
+    graph.insert(String::from("root_node"), toml::Value::String(root));
+    graph.insert(String::from("nodes"), toml::Value::Table(nodes));
+
+    Ok((graph, report))
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn convert(
+    id: &str,
+    source: &str,
+    report: &mut dyn FnMut(String),
+) -> toml::Table {
+    let (front_matter, body) = split_front_matter(source);
+    let mut node = toml::Table::new();
+    let mut title = None;
+    let mut links: Vec<String> = vec![];
+
+    for (key, values) in front_matter {
+        match key.as_str() {
+            "title" => title = values.first().cloned(),
+            "hidden" => {
+                if values.first().is_some_and(|v| v == "true") {
+                    node.insert(key, toml::Value::Boolean(true));
+                }
+            },
+            "summary" => {
+                if let Some(summary) = values.first() {
+                    node.insert(key, toml::Value::String(summary.clone()));
+                }
+            },
+            "links" => links.extend(values.iter().map(|v| {
+                make_id(v.trim_start_matches("[[").trim_end_matches("]]"))
+            })),
+            "id" | "aliases" => (),
+            _ => report(format!(" front matter key {key} was not converted")),
+        }
+    }
+
+    let mut converted: Vec<String> = vec![];
+    let mut fenced = false;
+    let mut emphasis = false;
+
+    for (number, line) in body.lines().enumerate() {
+        if line.trim_start().starts_with("```")
+            || line.trim_start().starts_with("~~~")
+        {
+            fenced = !fenced;
+            converted.push(String::from("`"));
+        } else if fenced {
+            converted.push(line.to_owned());
+        } else if title.is_none()
+            && let Some(heading) = line.strip_prefix("# ")
+        {
+            title = Some(heading.trim().to_owned());
+        } else {
+            let location = number.saturating_add(1);
+            let mut line_report = |message: &str| {
+                report(format!("{location}: {message}"));
+            };
+            emphasis |= line.contains("**") || line.contains("__");
+            converted.push(convert_line(line, &mut links, &mut line_report));
+        }
+    }
+
+    if fenced {
+        report(String::from(" unclosed code fence was closed at the end"));
+        converted.push(String::from("`"));
+    }
+
+    if emphasis {
+        report(String::from(" emphasis markers were kept as plain text"));
+    }
+
+    let text = converted.join("\n").trim().to_owned();
+    node.insert(String::from("text"), toml::Value::String(text));
+
+    if let Some(t) = title {
+        node.insert(String::from("title"), toml::Value::String(t));
+    }
+
+    // Keeps the first mention of each link, wherever the others are
+    let mut seen = HashSet::new();
+    links.retain(|link| link != id && seen.insert(link.clone()));
+    if !links.is_empty() {
+        node.insert(
+            String::from("links"),
+            toml::Value::Array(
+                links.into_iter().map(toml::Value::from).collect(),
+            ),
+        );
+    }
+
+    node
+}
+
+// Converts links outside of inline code spans, which are kept as they are
+fn convert_line(
+    line: &str,
+    links: &mut Vec<String>,
+    note: &mut dyn FnMut(&str),
+) -> String {
+    let mut inside_code = true;
+    line.split('`')
+        .map(|segment| {
+            inside_code = !inside_code;
+            if inside_code {
+                return segment.to_owned();
+            }
+
+            if strip_wikilinks(segment).contains('|') {
+                note("a literal | may be read as an anchor");
+            }
+
+            convert_markdown_links(
+                &convert_wikilinks(segment, links, note),
+                links,
+                note,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("`")
+}
+
+fn convert_wikilinks(
+    text: &str,
+    links: &mut Vec<String>,
+    note: &mut dyn FnMut(&str),
+) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some((before, after)) = rest.split_once("[[")
+        && let Some((inner, remaining)) = after.split_once("]]")
+    {
+        if before.ends_with('!') {
+            note("embeds are not supported and were kept as text");
+            out.push_str(before);
+            out.push_str("[[");
+            out.push_str(inner);
+            out.push_str("]]");
+            rest = remaining;
+            continue;
+        }
+
+        let (target, given) = inner.split_once('|').unwrap_or((inner, ""));
+        let (page, section) = target.split_once('#').unwrap_or((target, ""));
+        let label = if given.is_empty() { page } else { given };
+
+        if !section.is_empty() {
+            note("links to headings point to the whole node");
+        }
+
+        let id = make_id(page);
+        out.push_str(before);
+        out.push_str(&make_anchor(label, &id));
+        links.push(id);
+        rest = remaining;
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn convert_markdown_links(
+    text: &str,
+    links: &mut Vec<String>,
+    note: &mut dyn FnMut(&str),
+) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some((before, after)) = rest.split_once('[') {
+        let Some((label, url, remaining)) = after
+            .split_once("](")
+            .filter(|&(label, _)| !label.contains(['[', ']']))
+            .and_then(|(label, tail)| {
+                tail.split_once(')')
+                    .map(|(url, remaining)| (label, url, remaining))
+            })
+        else {
+            out.push_str(before);
+            out.push('[');
+            rest = after;
+            continue;
+        };
+
+        if before.ends_with('!') {
+            note("images are not supported and were kept as text");
+            out.push_str(before);
+            out.push_str(&["[", label, "](", url, ")"].concat());
+        } else if let Some(page) = url.strip_suffix(".md")
+            && !url.contains(':')
+        {
+            let id = make_id(page.rsplit('/').next().unwrap_or(page));
+            out.push_str(before);
+            out.push_str(&make_anchor(label, &id));
+            links.push(id);
+        } else {
+            out.push_str(before);
+            out.push_str(&make_anchor(label, url));
+        }
+        rest = remaining;
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn make_anchor(label: &str, destination: &str) -> String {
+    if label == destination {
+        format!("|{destination}|")
+    } else {
+        format!("|{label}|{destination}|")
+    }
+}
+
+// Anchor destinations end at whitespace, so IDs can't contain any
+fn make_id(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+fn strip_wikilinks(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+
+    while let Some((before, after)) = rest.split_once("[[")
+        && let Some((_inner, remaining)) = after.split_once("]]")
+    {
+        out.push_str(before);
+        rest = remaining;
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn split_front_matter(source: &str) -> (Vec<(String, Vec<String>)>, &str) {
+    let Some(rest) = source.strip_prefix("---\n") else {
+        return (vec![], source);
+    };
+    let Some((header, body)) = rest.split_once("\n---\n") else {
+        return (vec![], source);
+    };
+
+    let mut fields: Vec<(String, Vec<String>)> = vec![];
+
+    for line in header.lines() {
+        if let Some(item) = line.trim_start().strip_prefix("- ")
+            && line.starts_with(' ')
+            && let Some(&mut (_, ref mut values)) = fields.last_mut()
+        {
+            values.push(unquote(item));
+        } else if let Some((key, value)) = line.split_once(':')
+            && !line.starts_with(' ')
+        {
+            let raw = value.trim();
+            let values = if let Some(list) =
+                raw.strip_prefix('[').and_then(|v| v.strip_suffix(']'))
+            {
+                list.split(',')
+                    .map(unquote)
+                    .filter(|v| !v.is_empty())
+                    .collect()
+            } else if raw.is_empty() {
+                vec![]
+            } else {
+                vec![unquote(raw)]
+            };
+            fields.push((key.trim().to_owned(), values));
+        }
+    }
+
+    (fields, body)
+}
+
+fn unquote(value: &str) -> String {
+    let trimmed = value.trim();
+    if trimmed.starts_with('"') {
+        serde_json::from_str(trimmed).unwrap_or_else(|_| trimmed.to_owned())
+    } else {
+        trimmed.trim_matches('\'').to_owned()
+    }
+}
+
+fn wikilink(id: &str) -> String {
+    format!("[[{}]]", file_stem(id))
+}
+
+// JSON strings are valid YAML double-quoted scalars
+fn quote(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{syntax::serial::populate_graph, types::Edge};
+
+    use super::*;
+
+    #[test]
+    fn front_matter() {
+        let node = Node {
+            id: String::from("Quark"),
+            title: String::from("A \"quark\""),
+            text: String::from("Forms |hadrons|Hadron|."),
+            links: vec![String::from("Particle")],
+            hidden: true,
+            connections: Some(vec![
+                Edge {
+                    to: String::from("Particle"),
+                    from: String::from("Quark"),
+                    ..Edge::default()
+                },
+                Edge {
+                    to: String::from("Hadron"),
+                    anchor: String::from("hadrons"),
+                    from: String::from("Quark"),
+                    detached: true,
+                    ..Edge::default()
+                },
+            ]),
+            ..Node::default()
+        };
+
+        assert_eq!(
+            make_document(&node),
+            "---\n\
+            title: \"A \\\"quark\\\"\"\n\
+            id: \"Quark\"\n\
+            hidden: true\n\
+            aliases: [\"A \\\"quark\\\"\"]\n\
+            links: [\"[[Particle]]\"]\n\
+            connections:\n  \
+              - to: \"[[Hadron]]\"\n    \
+                anchor: \"hadrons\"\n\
+            ---\n\
+            \n\
+            Forms [[Hadron|hadrons]].\n"
+        );
+    }
+
+    #[test]
+    fn wikilinks_to_anchors() {
+        let mut links = vec![];
+        let mut notes = vec![];
+        let line = convert_line(
+            "See [[Quark]], [[Hadron|these]] and [[Big Bang#Start]] \
+            but not `[[code]]`, also [docs](https://a.b) and [x](Other.md).",
+            &mut links,
+            &mut |note| notes.push(note.to_owned()),
+        );
+        assert_eq!(
+            line,
+            "See |Quark|, |these|Hadron| and |Big Bang|Big_Bang| \
+            but not `[[code]]`, also |docs|https://a.b| and |x|Other|."
+        );
+        assert_eq!(links, ["Quark", "Hadron", "Big_Bang", "Other"]);
+        assert_eq!(notes, ["links to headings point to the whole node"]);
+    }
+
+    #[test]
+    fn unconvertible_syntax() {
+        let mut notes = vec![];
+        let line = convert_line(
+            "| a | b | ![[image.png]] ![alt](x.png)",
+            &mut vec![],
+            &mut |note| notes.push(note.to_owned()),
+        );
+        assert_eq!(line, "| a | b | ![[image.png]] ![alt](x.png)");
+        assert_eq!(
+            notes,
+            [
+                "a literal | may be read as an anchor",
+                "embeds are not supported and were kept as text",
+                "images are not supported and were kept as text",
+            ]
+        );
+    }
+
+    #[test]
+    fn convert_file() {
+        let source = "---\ntitle: \"Quarks\"\nhidden: true\nlinks:\n  - \
+            \"[[Particle]]\"\ncssclass: wide\n---\n# Ignored\n\n**Bold** \
+            [[Hadron]]\n\n```rust\nlet x = 1;\n```\n";
+        let mut report = vec![];
+        let node = convert("Quark", source, &mut |note| report.push(note));
+
+        assert_eq!(
+            node.get("title").and_then(toml::Value::as_str),
+            Some("Quarks")
+        );
+        assert_eq!(
+            node.get("hidden").and_then(toml::Value::as_bool),
+            Some(true)
+        );
+        assert_eq!(
+            node.get("text").and_then(toml::Value::as_str),
+            Some("# Ignored\n\n**Bold** |Hadron|\n\n`\nlet x = 1;\n`")
+        );
+        assert_eq!(
+            node.get("links").unwrap(),
+            &toml::Value::from(vec![
+                String::from("Particle"),
+                String::from("Hadron")
+            ])
+        );
+        assert_eq!(
+            report,
+            [
+                " front matter key cssclass was not converted",
+                " emphasis markers were kept as plain text",
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_links() {
+        let source = "---\nlinks:\n  - \"[[Hadron]]\"\n---\n\
+            [[Quark]] and [[Gluon]], then [[Hadron]] and [[Gluon]] again.";
+        let node = convert("Quark", source, &mut |_| ());
+        assert_eq!(
+            node.get("links").unwrap(),
+            &toml::Value::from(vec![
+                String::from("Hadron"),
+                String::from("Gluon")
+            ])
+        );
+    }
+
+    #[test]
+    fn title_from_heading() {
+        let node = convert("Quark", "# A Quark\nText.", &mut |_| ());
+        assert_eq!(
+            node.get("title").and_then(toml::Value::as_str),
+            Some("A Quark")
+        );
+        assert_eq!(
+            node.get("text").and_then(toml::Value::as_str),
+            Some("Text.")
+        );
+    }
+
+    #[test]
+    fn import_directory() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-markdown-import-{}", std::process::id()));
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::write(directory.join("index.md"), "Go to [[My Note]].").unwrap();
+        fs::write(directory.join("nested/My Note.md"), "Back to [[index]].")
+            .unwrap();
+        fs::write(directory.join("ignored.txt"), "Not markdown.").unwrap();
+
+        let (graph, report) = import(&directory).unwrap();
+        let text = toml::to_string(&graph).unwrap();
+        let parsed = crate::syntax::serial::deserialize_graph(
+            &crate::syntax::serial::Format::TOML,
+            &text,
+        );
+
+        assert!(parsed.meta.messages.is_empty());
+        assert_eq!(parsed.root_node, "index");
+        assert_eq!(parsed.nodes.len(), 2);
+        assert_eq!(
+            parsed.nodes.get("index").unwrap().text,
+            "Go to |My Note|My_Note|."
+        );
+        assert_eq!(parsed.nodes.get("My_Note").unwrap().links, ["index"]);
+        assert_eq!(report.len(), 1);
+        assert!(
+            report
+                .first()
+                .unwrap()
+                .contains(r#"ID changed from "My Note" to My_Note"#)
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_stem("a/b\\c"), "a-b-c");
+
+        let node = Node {
+            id: String::from("Quark"),
+            title: String::from("Quark"),
+            links: vec![String::from("Particles/Quarks")],
+            ..Node::default()
+        };
+        assert!(
+            make_document(&node).contains("links: [\"[[Particles-Quarks]]\"]")
+        );
+    }
+
+    #[test]
+    fn file_name_collision() {
+        let mut graph = Graph::default();
+        for id in ["a/b", "a-b"] {
+            let node = Node {
+                id: String::from(id),
+                ..Node::default()
+            };
+            graph.nodes.insert(String::from(id), node);
+        }
+        let directory = std::env::temp_dir()
+            .join(format!("en-markdown-collision-{}", std::process::id()));
+
+        let error = export(&graph, &directory).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("a-b and a/b"));
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn export_graph() {
+        let graph = populate_graph();
+        let directory = std::env::temp_dir()
+            .join(format!("en-markdown-export-{}", std::process::id()));
+
+        let count = export(&graph, &directory).unwrap();
+        assert_eq!(count, graph.nodes.len());
+
+        let syntax = fs::read_to_string(directory.join("Syntax.md")).unwrap();
+        assert!(syntax.starts_with("---\ntitle: \"Syntax\"\n"));
+        assert!(syntax.contains("See [[AnchorSyntax]] for some caveats"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,18 +1,50 @@
 use std::collections::HashMap;
 
 use crate::{
+    prelude::*,
     syntax::command::Arguments,
-    types::{Edge, Graph, Node},
+    types::{Edge, Graph, Meta, Node},
 };
 
+pub mod edit;
+pub mod journal;
+pub mod migrate;
+pub mod settings;
+
+/// Reads and modulates the graph, with the settings en was started with
+/// from the settings file, environment and arguments applied over its
+/// config.
 pub fn populate_graph() -> Graph {
+    let graph = modulate_graph(read_source());
+
+    match settings::layer(graph.meta.config.clone()) {
+        Ok(config) => Graph {
+            meta: Meta {
+                config,
+                ..graph.meta
+            },
+            ..graph
+        },
+        Err(e) => {
+            log!("Ignored settings: {e}");
+            graph
+        },
+    }
+}
+
+/// Reads the graph as written by its author, without derived data, upgraded
+/// to the current format version.
+pub fn read_source() -> Graph {
     let args = Arguments::new().parse();
     let toml_source = match std::fs::read_to_string(args.graph_path) {
         Ok(s) => s,
         Err(e) => format!("Error: {e}"),
     };
-    let graph = deserialize_graph(&Format::TOML, &toml_source);
+    migrate::load(&toml_source)
+}
 
+/// Derives edges, titles, detached flags and lookup maps from source nodes.
+pub fn modulate_graph(graph: Graph) -> Graph {
     let nodes = modulate_nodes(&graph.nodes);
 
     Graph {
@@ -65,6 +97,7 @@ fn modulate_nodes(old_nodes: &HashMap<String, Node>) -> HashMap<String, Node> {
                 from: key.clone(),
                 to: link.clone(),
                 anchor: String::new(),
+                kind: String::new(),
                 detached: !old_nodes.contains_key(link),
             });
         }
@@ -103,6 +136,11 @@ fn make_incoming(nodes: &HashMap<String, Node>) -> HashMap<String, Vec<Edge>> {
         }
     }
 
+    // Nodes are visited in no particular order
+    for edges in incoming.values_mut() {
+        edges.sort_by(|a, b| a.from.cmp(&b.from));
+    }
+
     incoming
 }
 
@@ -124,6 +162,34 @@ pub fn serialize_graph(out_format: &Format, graph: &Graph) -> String {
     }
 }
 
+// A single node along with the edges pointing to it
+#[derive(serde::Serialize)]
+struct NodeWithIncoming<'a> {
+    #[serde(flatten)]
+    node: &'a Node,
+    incoming: &'a [Edge],
+}
+
+/// Serializes a node with its outgoing connections and `incoming` edges.
+pub fn serialize_node(
+    out_format: &Format,
+    node: &Node,
+    incoming: &[Edge],
+) -> String {
+    let wrapped = NodeWithIncoming { node, incoming };
+
+    match *out_format {
+        Format::TOML => match toml::to_string(&wrapped) {
+            Ok(s) => s,
+            Err(e) => e.to_string(),
+        },
+        Format::JSON => match serde_json::to_string(&wrapped) {
+            Ok(s) => s,
+            Err(e) => e.to_string(),
+        },
+    }
+}
+
 pub fn deserialize_graph(in_format: &Format, serial: &str) -> Graph {
     match *in_format {
         Format::TOML => match toml::from_str(serial) {
@@ -170,6 +236,54 @@ mod tests {
         assert!(message.contains("expected value at line 1 column 1"));
     }
 
+    #[test]
+    fn source_round_trip() {
+        let source = read_source();
+        for (format, key) in [
+            (Format::TOML, "lowercase_keymap ="),
+            (Format::JSON, r#""lowercase_keymap":"#),
+        ] {
+            let serialized = serialize_graph(&format, &source);
+            assert!(!serialized.contains(key));
+            assert_eq!(deserialize_graph(&format, &serialized), source);
+        }
+    }
+
+    #[test]
+    fn node_formats() {
+        let node = Node {
+            id: String::from("Quark"),
+            text: String::from("Forms hadrons."),
+            connections: Some(vec![Edge {
+                to: String::from("Hadron"),
+                from: String::from("Quark"),
+                ..Edge::default()
+            }]),
+            ..Node::default()
+        };
+        let incoming = [Edge {
+            to: String::from("Quark"),
+            from: String::from("Gluon"),
+            ..Edge::default()
+        }];
+
+        let json: serde_json::Value = serde_json::from_str(&serialize_node(
+            &Format::JSON,
+            &node,
+            &incoming,
+        ))
+        .unwrap();
+        assert_eq!(json.pointer("/id").unwrap(), "Quark");
+        assert_eq!(json.pointer("/connections/0/to").unwrap(), "Hadron");
+        assert_eq!(json.pointer("/incoming/0/from").unwrap(), "Gluon");
+
+        let toml: toml::Table =
+            toml::from_str(&serialize_node(&Format::TOML, &node, &incoming))
+                .unwrap();
+        assert_eq!(toml.get("text").unwrap().as_str(), Some("Forms hadrons."));
+        assert!(toml.get("incoming").unwrap().is_array());
+    }
+
     #[test]
     fn detached_node() {
         let node = Node {
@@ -178,10 +292,15 @@ mod tests {
             title: String::new(),
             links: vec![String::new()],
             hidden: false,
+            summary: String::new(),
+            tags: vec![],
+            template: String::new(),
+            classes: vec![],
             connections: Some(vec![Edge {
                 anchor: String::from("SomeAnchor"),
                 from: String::new(),
                 to: String::new(),
+                kind: String::new(),
                 detached: false,
             }]),
         };
@@ -0,0 +1,1145 @@
+use std::{
+    collections::HashSet,
+    error, fmt, fs,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    io,
+    path::Path,
+};
+
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::{
+    syntax::{
+        content::{
+            self,
+            parser::{segment, token::Token},
+        },
+        serial::{journal, migrate, modulate_graph},
+    },
+    types::Node,
+};
+
+/// The fields of a node that can be edited from a form.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct NodeFields {
+    pub text: String,
+    pub title: String,
+    pub links: Vec<String>,
+    pub hidden: bool,
+}
+
+/// A change to the graph, applied to its source document so that anything
+/// it doesn't touch keeps its comments and formatting.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Mutation {
+    AddNode {
+        id: String,
+        text: String,
+    },
+    /// Also updates links, connections, anchors in node text and the root
+    /// node pointing to it.
+    RenameNode {
+        from: String,
+        to: String,
+    },
+    /// Only applies to nodes nothing else links to.
+    DeleteNode {
+        id: String,
+    },
+    /// Edges without an anchor or kind are added to `links`, others to
+    /// `connections`.
+    AddEdge {
+        from: String,
+        to: String,
+        anchor: String,
+        kind: String,
+    },
+    /// Removes every link and connection from one node to another.
+    RemoveEdge {
+        from: String,
+        to: String,
+    },
+    /// Sets `text`, `title` or `summary` to `value`, `hidden` to `value`
+    /// read as a boolean or `links` to `value` split at whitespace.
+    SetField {
+        id: String,
+        field: String,
+        value: String,
+    },
+    /// Writes the fields of a node, creating it if it doesn't exist.
+    UpdateNode {
+        id: String,
+        fields: NodeFields,
+    },
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Mutation::AddNode { ref id, .. } => write!(f, "add node {id}"),
+            Mutation::RenameNode { ref from, ref to } => {
+                write!(f, "rename node {from} to {to}")
+            },
+            Mutation::DeleteNode { ref id } => write!(f, "delete node {id}"),
+            Mutation::AddEdge {
+                ref from, ref to, ..
+            } => write!(f, "add edge from {from} to {to}"),
+            Mutation::RemoveEdge { ref from, ref to } => {
+                write!(f, "remove edges from {from} to {to}")
+            },
+            Mutation::SetField {
+                ref id, ref field, ..
+            } => write!(f, "set {field} of node {id}"),
+            Mutation::UpdateNode { ref id, .. } => {
+                write!(f, "update node {id}")
+            },
+        }
+    }
+}
+
+/// Applies `mutations` in order to the graph file at `path` while holding
+/// its lock, replacing the file atomically and recording the write in its
+/// journal. Nothing is written if any of them fails.
+///
+/// # Errors
+/// Fails if the file can't be locked, read, parsed or written, if a mutation
+/// can't be applied or if the journal can't be written.
+pub fn save(path: &Path, mutations: &[Mutation]) -> io::Result<()> {
+    save_checked(path, mutations, |_before| Ok(()))
+}
+
+/// Like [`save`], but only if node `id` still has `version`, as made by
+/// [`make_version`], when the graph is read under its lock. Otherwise the
+/// changes saved since `version` was made would be lost.
+///
+/// # Errors
+/// Fails with a [`Stale`] error if the node changed, or for the same
+/// reasons as [`save`].
+pub fn save_unchanged(
+    path: &Path,
+    mutations: &[Mutation],
+    id: &str,
+    version: &str,
+) -> io::Result<()> {
+    save_checked(path, mutations, |before| {
+        let graph = modulate_graph(migrate::load(before));
+        if make_version(graph.nodes.get(id)) == version {
+            Ok(())
+        } else {
+            Err(io::Error::other(Stale { id: id.to_owned() }))
+        }
+    })
+}
+
+/// A short digest of a node as read from the graph, or of its absence,
+/// which changes whenever the node does.
+pub fn make_version(node: Option<&Node>) -> String {
+    let mut hasher = DefaultHasher::new();
+    node.map(serde_json::to_string)
+        .map(Result::ok)
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// The error of [`save_unchanged`] when the node changed after its version
+/// was made.
+#[derive(Debug)]
+pub struct Stale {
+    pub id: String,
+}
+
+impl fmt::Display for Stale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Node {} was changed since it was read", self.id)
+    }
+}
+
+impl error::Error for Stale {}
+
+// Runs `check` on the graph source as read under the lock, before applying
+// anything
+fn save_checked(
+    path: &Path,
+    mutations: &[Mutation],
+    check: impl FnOnce(&str) -> io::Result<()>,
+) -> io::Result<()> {
+    let _lock = journal::Lock::acquire(path)?;
+    let before = fs::read_to_string(path)?;
+    check(&before)?;
+
+    let mut document = parse(&before)?;
+    for mutation in mutations {
+        apply(&mut document, mutation)?;
+    }
+
+    let after = document.to_string();
+    journal::commit(
+        path,
+        mutations.iter().map(ToString::to_string).collect(),
+        &before,
+        &after,
+    )
+}
+
+/// Parses a graph file into a document that keeps its formatting.
+///
+/// # Errors
+/// Fails if `source` isn't valid TOML.
+pub fn parse(source: &str) -> io::Result<DocumentMut> {
+    source
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Applies a single mutation to `document`.
+///
+/// # Errors
+/// Fails if a node the mutation refers to doesn't exist, if a node it would
+/// create already exists, if it would delete the root node or remove edges
+/// that don't exist, if a field or value isn't valid, or if `nodes` or a
+/// node aren't tables.
+pub fn apply(
+    document: &mut DocumentMut,
+    mutation: &Mutation,
+) -> io::Result<()> {
+    match *mutation {
+        Mutation::AddNode { ref id, ref text } => {
+            if node_mut(document, id).is_ok() {
+                return Err(already_exists(id));
+            }
+            update_node(
+                document,
+                id,
+                &NodeFields {
+                    text: text.clone(),
+                    ..NodeFields::default()
+                },
+            )
+        },
+        Mutation::RenameNode { ref from, ref to } => {
+            rename_node(document, from, to)
+        },
+        Mutation::DeleteNode { ref id } => {
+            if document.get("root_node").and_then(Item::as_str) == Some(id) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Node {id} is the root node"),
+                ));
+            }
+            let nodes = nodes_mut(document)?;
+            if !nodes.contains_key(id) {
+                return Err(not_found(id));
+            }
+
+            let referrers = find_referrers(nodes, id);
+            if !referrers.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Node {id} is linked from {}, remove those links \
+                            before deleting it",
+                        referrers.join(", ")
+                    ),
+                ));
+            }
+
+            nodes.remove(id);
+            Ok(())
+        },
+        Mutation::AddEdge {
+            ref from,
+            ref to,
+            ref anchor,
+            ref kind,
+        } => {
+            let inline = nodes_mut(document)?
+                .get(from)
+                .is_some_and(Item::is_inline_table);
+            add_edge(node_mut(document, from)?, inline, to, anchor, kind)
+        },
+        Mutation::RemoveEdge { ref from, ref to } => {
+            remove_edges(node_mut(document, from)?, from, to)
+        },
+        Mutation::SetField {
+            ref id,
+            ref field,
+            ref value,
+        } => set_field(node_mut(document, id)?, field, value),
+        Mutation::UpdateNode { ref id, ref fields } => {
+            update_node(document, id, fields)
+        },
+    }
+}
+
+/// Applies `fields` to node `id` in `document`. Empty titles and links and a
+/// false hidden flag are removed rather than written out.
+///
+/// # Errors
+/// Fails if `nodes` or the node aren't tables.
+pub fn update_node(
+    document: &mut DocumentMut,
+    id: &str,
+    fields: &NodeFields,
+) -> io::Result<()> {
+    let inline = document.get("nodes").is_some_and(Item::is_inline_table);
+
+    let node = nodes_mut(document)?
+        .entry(id)
+        .or_insert_with(|| {
+            if inline {
+                Item::Value(Value::InlineTable(toml_edit::InlineTable::new()))
+            } else {
+                toml_edit::table()
+            }
+        })
+        .as_table_like_mut()
+        .ok_or_else(|| invalid(&format!("Node {id} is not a table")))?;
+
+    set(node, "text", Some(Value::from(fields.text.as_str())));
+    set(
+        node,
+        "title",
+        (!fields.title.is_empty()).then(|| Value::from(fields.title.as_str())),
+    );
+    set(
+        node,
+        "links",
+        (!fields.links.is_empty())
+            .then(|| Value::Array(fields.links.iter().collect())),
+    );
+    set(node, "hidden", fields.hidden.then(|| Value::from(true)));
+
+    Ok(())
+}
+
+fn nodes_mut(document: &mut DocumentMut) -> io::Result<&mut dyn TableLike> {
+    document
+        .entry("nodes")
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_like_mut()
+        .ok_or_else(|| invalid("Nodes are not a table"))
+}
+
+fn node_mut<'a>(
+    document: &'a mut DocumentMut,
+    id: &str,
+) -> io::Result<&'a mut dyn TableLike> {
+    nodes_mut(document)?
+        .get_mut(id)
+        .ok_or_else(|| not_found(id))?
+        .as_table_like_mut()
+        .ok_or_else(|| invalid(&format!("Node {id} is not a table")))
+}
+
+fn rename_node(
+    document: &mut DocumentMut,
+    from: &str,
+    to: &str,
+) -> io::Result<()> {
+    let nodes = nodes_mut(document)?;
+
+    if nodes.contains_key(to) {
+        return Err(already_exists(to));
+    }
+
+    // Tables keep their position in the document when moved to a new key
+    let node = nodes.remove(from).ok_or_else(|| not_found(from))?;
+    nodes.insert(to, node);
+    let ids = node_ids(nodes);
+
+    for (node_key, item) in nodes.iter_mut() {
+        let Some(table) = item.as_table_like_mut() else {
+            continue;
+        };
+
+        if let Some(text) = table.get_mut("text").and_then(Item::as_value_mut)
+            && let Some(written) = text.as_str()
+        {
+            let retargeted = retarget_anchors(written, from, to, &ids)
+                .ok_or_else(|| {
+                    invalid(&format!(
+                        "Node {} links to {from} in its text in a way that \
+                            can't be rewritten",
+                        node_key.get()
+                    ))
+                })?;
+            let mut new = Value::from(retargeted);
+            if !same(text, &new) {
+                *new.decor_mut() = text.decor().clone();
+                *text = new;
+            }
+        }
+
+        if let Some(links) = table.get_mut("links").and_then(Item::as_array_mut)
+        {
+            links.iter_mut().for_each(|link| rename(link, from, to));
+        }
+
+        for connection in connections_mut(table) {
+            for key in ["to", "from"] {
+                if let Some(value) =
+                    connection.get_mut(key).and_then(Item::as_value_mut)
+                {
+                    rename(value, from, to);
+                }
+            }
+        }
+    }
+
+    if let Some(root) =
+        document.get_mut("root_node").and_then(Item::as_value_mut)
+    {
+        rename(root, from, to);
+    }
+
+    Ok(())
+}
+
+// Points anchors in `text` that lead to node `from` at `to` instead,
+// keeping their labels. A destination is replaced where there is one, and
+// an anchor whose label is also its destination gets one after its closing
+// pipe. Each change is checked by reading the text again, and `None` means
+// some anchor couldn't be rewritten either way.
+fn retarget_anchors(
+    text: &str,
+    from: &str,
+    to: &str,
+    ids: &HashSet<String>,
+) -> Option<String> {
+    let mut remaining = count_anchors(text, from, ids);
+    if remaining == 0 {
+        return Some(text.to_owned());
+    }
+
+    let labels = content::plain(text);
+    let mut segments = segment::segment(text);
+    let mut index: usize = 1;
+
+    while remaining > 0 && index < segments.len() {
+        let is_candidate =
+            segments.get(index).is_some_and(|s| leads_to(s, from, ids))
+                && segments
+                    .get(index.saturating_sub(1))
+                    .is_some_and(|s| s == "|");
+
+        if is_candidate {
+            let replaced = splice(&segments, index, 1, &[to]);
+            let appended =
+                splice(&segments, index.saturating_add(2), 0, &[to, "|"]);
+
+            for attempt in [replaced, appended] {
+                let joined = attempt.concat();
+                let left = count_anchors(&joined, from, ids);
+                if left < remaining && content::plain(&joined) == labels {
+                    remaining = left;
+                    segments = attempt;
+                    break;
+                }
+            }
+        }
+
+        index = index.saturating_add(1);
+    }
+
+    (remaining == 0).then(|| segments.concat())
+}
+
+fn count_anchors(text: &str, id: &str, ids: &HashSet<String>) -> usize {
+    content::tokenize(text)
+        .iter()
+        .filter(|token| {
+            matches!(**token, Token::Anchor(ref anchor)
+                if anchor.node_id().is_some_and(|target| leads_to(&target, id, ids)))
+        })
+        .count()
+}
+
+// Whether an anchor to `target` leads to node `id`. Like the node pages it
+// opens, a lowercase ID falls back to the node it's the lowercase form of
+// unless some node is named that exactly.
+fn leads_to(target: &str, id: &str, ids: &HashSet<String>) -> bool {
+    target == id || (!ids.contains(target) && target == id.to_lowercase())
+}
+
+fn node_ids(nodes: &dyn TableLike) -> HashSet<String> {
+    nodes.iter().map(|(key, _)| key.to_owned()).collect()
+}
+
+// A copy of `segments` with `removed` of them at `index` replaced by `added`
+fn splice(
+    segments: &[String],
+    index: usize,
+    removed: usize,
+    added: &[&str],
+) -> Vec<String> {
+    let mut copy = segments.to_vec();
+    if index <= copy.len() {
+        let end = index.saturating_add(removed).min(copy.len());
+        copy.splice(index..end, added.iter().map(|&s| s.to_owned()));
+    }
+    copy
+}
+
+// IDs of other nodes whose links, connections or text lead to `id`
+fn find_referrers(nodes: &dyn TableLike, id: &str) -> Vec<String> {
+    let ids = node_ids(nodes);
+    nodes
+        .iter()
+        .filter(|&(key, _)| key != id)
+        .filter(|&(_, item)| {
+            let Some(table) = item.as_table_like() else {
+                return false;
+            };
+
+            let links =
+                table.get("links").and_then(Item::as_array).is_some_and(
+                    |links| links.iter().any(|link| link.as_str() == Some(id)),
+                );
+            let leads_to_id = |connection: &dyn TableLike| {
+                ["to", "from"].iter().any(|&key| {
+                    connection.get(key).and_then(Item::as_str) == Some(id)
+                })
+            };
+            let connections = table.get("connections").is_some_and(|field| {
+                if let Some(tables) = field.as_array_of_tables() {
+                    tables.iter().any(|connection| leads_to_id(connection))
+                } else {
+                    field.as_array().is_some_and(|array| {
+                        array
+                            .iter()
+                            .filter_map(Value::as_inline_table)
+                            .any(|connection| leads_to_id(connection))
+                    })
+                }
+            });
+            let text = table
+                .get("text")
+                .and_then(Item::as_str)
+                .is_some_and(|text| count_anchors(text, id, &ids) > 0);
+
+            links || connections || text
+        })
+        .map(|(key, _)| key.to_owned())
+        .collect()
+}
+
+fn add_edge(
+    node: &mut dyn TableLike,
+    inline: bool,
+    to: &str,
+    anchor: &str,
+    kind: &str,
+) -> io::Result<()> {
+    if anchor.is_empty() && kind.is_empty() {
+        let links = node
+            .entry("links")
+            .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+            .as_array_mut()
+            .ok_or_else(|| invalid("Links are not an array"))?;
+
+        if !links.iter().any(|link| link.as_str() == Some(to)) {
+            links.push(to);
+        }
+        return Ok(());
+    }
+
+    let mut connection = toml_edit::InlineTable::new();
+    connection.insert("to", Value::from(to));
+    if !anchor.is_empty() {
+        connection.insert("anchor", Value::from(anchor));
+    }
+    if !kind.is_empty() {
+        connection.insert("kind", Value::from(kind));
+    }
+
+    // Node tables get `[[nodes.ID.connections]]`, inline nodes an array
+    let connections = node.entry("connections").or_insert_with(|| {
+        if inline {
+            toml_edit::value(toml_edit::Array::new())
+        } else {
+            Item::ArrayOfTables(toml_edit::ArrayOfTables::new())
+        }
+    });
+
+    if let Some(tables) = connections.as_array_of_tables_mut() {
+        tables.push(connection.into_table());
+    } else if let Some(array) = connections.as_array_mut() {
+        array.push(connection);
+    } else {
+        return Err(invalid("Connections are not an array"));
+    }
+
+    Ok(())
+}
+
+fn remove_edges(
+    node: &mut dyn TableLike,
+    from: &str,
+    to: &str,
+) -> io::Result<()> {
+    let mut removed = false;
+
+    if let Some(links) = node.get_mut("links").and_then(Item::as_array_mut) {
+        let before = links.len();
+        links.retain(|link| link.as_str() != Some(to));
+        removed |= links.len() != before;
+    }
+
+    match node.get_mut("connections") {
+        Some(&mut Item::ArrayOfTables(ref mut tables)) => {
+            let before = tables.len();
+            tables.retain(|table| {
+                table.get("to").and_then(Item::as_str) != Some(to)
+            });
+            removed |= tables.len() != before;
+        },
+        Some(&mut Item::Value(Value::Array(ref mut array))) => {
+            let before = array.len();
+            array.retain(|value| {
+                value
+                    .as_inline_table()
+                    .and_then(|table| table.get("to"))
+                    .and_then(Value::as_str)
+                    != Some(to)
+            });
+            removed |= array.len() != before;
+        },
+        _ => (),
+    }
+
+    for key in ["links", "connections"] {
+        if node.get(key).is_some_and(is_empty_array) {
+            node.remove(key);
+        }
+    }
+
+    if removed {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Node {from} has no edges to {to}"),
+        ))
+    }
+}
+
+fn set_field(
+    node: &mut dyn TableLike,
+    field: &str,
+    value: &str,
+) -> io::Result<()> {
+    let new = match field {
+        "text" | "title" | "summary" | "template" => Value::from(value),
+        "hidden" => Value::from(value.parse::<bool>().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Field hidden must be true or false: {e}"),
+            )
+        })?),
+        "links" | "tags" | "classes" => {
+            Value::Array(value.split_whitespace().collect())
+        },
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown node field {field}"),
+            ));
+        },
+    };
+
+    set(node, field, Some(new));
+    Ok(())
+}
+
+fn connections_mut(node: &mut dyn TableLike) -> Vec<&mut dyn TableLike> {
+    match node.get_mut("connections") {
+        Some(&mut Item::ArrayOfTables(ref mut tables)) => tables
+            .iter_mut()
+            .map(|connection| -> &mut dyn TableLike { connection })
+            .collect(),
+        Some(&mut Item::Value(Value::Array(ref mut array))) => array
+            .iter_mut()
+            .filter_map(Value::as_inline_table_mut)
+            .map(|connection| -> &mut dyn TableLike { connection })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// Replaces a key only if its value changed, keeping the comments and
+// whitespace around it
+fn set(table: &mut dyn TableLike, key: &str, value: Option<Value>) {
+    let Some(mut new) = value else {
+        table.remove(key);
+        return;
+    };
+
+    if let Some(old) = table.get_mut(key).and_then(Item::as_value_mut) {
+        if !same(old, &new) {
+            *new.decor_mut() = old.decor().clone();
+            *old = new;
+        }
+    } else {
+        table.insert(key, Item::Value(new));
+    }
+}
+
+fn rename(value: &mut Value, from: &str, to: &str) {
+    if value.as_str() == Some(from) {
+        let mut new = Value::from(to);
+        *new.decor_mut() = value.decor().clone();
+        *value = new;
+    }
+}
+
+fn same(old: &Value, new: &Value) -> bool {
+    if let (Some(a), Some(b)) = (old.as_array(), new.as_array()) {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| same(x, y))
+    } else if let (Some(a), Some(b)) = (old.as_str(), new.as_str()) {
+        a == b
+    } else {
+        old.as_bool().is_some_and(|a| new.as_bool() == Some(a))
+    }
+}
+
+fn is_empty_array(item: &Item) -> bool {
+    item.as_array().is_some_and(toml_edit::Array::is_empty)
+        || item
+            .as_array_of_tables()
+            .is_some_and(toml_edit::ArrayOfTables::is_empty)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn not_found(id: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Could not find node ID {id}"),
+    )
+}
+
+fn already_exists(id: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Node {id} already exists"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "root_node = \"A\"
+
+# First node
+[nodes.A]
+text = 'Old.' # kept
+links = [ \"B\" ]
+
+[nodes.B]
+text = \"\"\"
+Untouched.
+\"\"\"
+hidden = true
+
+[[nodes.B.connections]]
+to = \"A\" # back
+anchor = \"Untouched\"
+";
+
+    fn apply_to_source(mutation: &Mutation) -> io::Result<String> {
+        let mut document = parse(SOURCE).unwrap();
+        apply(&mut document, mutation)?;
+        Ok(document.to_string())
+    }
+
+    fn update(id: &str, fields: NodeFields) -> String {
+        apply_to_source(&Mutation::UpdateNode {
+            id: String::from(id),
+            fields,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn update_existing() {
+        let edited = update(
+            "A",
+            NodeFields {
+                text: String::from("New."),
+                title: String::from("Node A"),
+                links: vec![String::from("B")],
+                hidden: false,
+            },
+        );
+
+        assert_eq!(
+            edited,
+            SOURCE.replace(
+                "text = 'Old.' # kept\nlinks = [ \"B\" ]\n",
+                "text = \"New.\" # kept\nlinks = [ \"B\" ]\ntitle = \"Node A\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn unchanged_node() {
+        let edited = update(
+            "B",
+            NodeFields {
+                text: String::from("Untouched.\n"),
+                hidden: true,
+                ..NodeFields::default()
+            },
+        );
+        assert_eq!(edited, SOURCE);
+    }
+
+    #[test]
+    fn remove_defaults() {
+        let edited = update(
+            "B",
+            NodeFields {
+                text: String::from("Untouched.\n"),
+                ..NodeFields::default()
+            },
+        );
+        assert_eq!(edited, SOURCE.replace("hidden = true\n", ""));
+    }
+
+    #[test]
+    fn create_node() {
+        let edited = update(
+            "C",
+            NodeFields {
+                text: String::from("Two\nlines.\n"),
+                links: vec![String::from("A"), String::from("B")],
+                ..NodeFields::default()
+            },
+        );
+
+        assert!(edited.starts_with(SOURCE));
+        assert!(edited.ends_with(
+            "\n[nodes.C]\ntext = \"\"\"\nTwo\nlines.\n\"\"\"\n\
+            links = [\"A\", \"B\"]\n"
+        ));
+
+        let graph: crate::types::Graph = toml::from_str(&edited).unwrap();
+        assert_eq!(graph.nodes.get("C").unwrap().text, "Two\nlines.\n");
+    }
+
+    #[test]
+    fn add_node() {
+        let edited = apply_to_source(&Mutation::AddNode {
+            id: String::from("C"),
+            text: String::from("New."),
+        })
+        .unwrap();
+        assert_eq!(edited, format!("{SOURCE}\n[nodes.C]\ntext = \"New.\"\n"));
+
+        let error = apply_to_source(&Mutation::AddNode {
+            id: String::from("A"),
+            text: String::new(),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn rename_node() {
+        let edited = apply_to_source(&Mutation::RenameNode {
+            from: String::from("A"),
+            to: String::from("Z"),
+        })
+        .unwrap();
+
+        assert_eq!(
+            edited,
+            SOURCE
+                .replace("root_node = \"A\"", "root_node = \"Z\"")
+                .replace("[nodes.A]", "[nodes.Z]")
+                .replace("to = \"A\" # back", "to = \"Z\" # back")
+        );
+    }
+
+    #[test]
+    fn rename_anchors() {
+        let source = "[nodes.A]
+text = \"\"\"
+See |Old| and |the old node|Old or Old|Old here.
+Also |Older|, |Old|, |old| and |OLD|.
+\"\"\"
+
+[nodes.Old]
+text = \"Links to |itself|Old\"
+";
+        let mut document = parse(source).unwrap();
+        apply(
+            &mut document,
+            &Mutation::RenameNode {
+                from: String::from("Old"),
+                to: String::from("New"),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "[nodes.A]
+text = \"\"\"
+See |Old|New| and |the old node|New or Old|New here.
+Also |Older|, |Old|New|, |old|New| and |OLD|.
+\"\"\"
+
+[nodes.New]
+text = \"Links to |itself|New\"
+"
+        );
+    }
+
+    #[test]
+    fn rename_to_existing() {
+        let error = apply_to_source(&Mutation::RenameNode {
+            from: String::from("A"),
+            to: String::from("B"),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn delete_node() {
+        let linked = apply_to_source(&Mutation::DeleteNode {
+            id: String::from("B"),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(linked.kind(), io::ErrorKind::InvalidInput);
+        assert!(linked.to_string().contains("linked from A"));
+
+        let mut document = parse(SOURCE).unwrap();
+        for mutation in [
+            Mutation::RemoveEdge {
+                from: String::from("A"),
+                to: String::from("B"),
+            },
+            Mutation::DeleteNode {
+                id: String::from("B"),
+            },
+        ] {
+            apply(&mut document, &mutation).unwrap();
+        }
+        assert!(!document.to_string().contains("[nodes.B]"));
+
+        let root = apply_to_source(&Mutation::DeleteNode {
+            id: String::from("A"),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(root.kind(), io::ErrorKind::InvalidInput);
+
+        let missing = apply_to_source(&Mutation::DeleteNode {
+            id: String::from("C"),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn add_edges() {
+        let mut document = parse(SOURCE).unwrap();
+        for (to, anchor, kind) in [("C", "", ""), ("B", "", ""), ("D", "d", "")]
+        {
+            apply(
+                &mut document,
+                &Mutation::AddEdge {
+                    from: String::from("A"),
+                    to: String::from(to),
+                    anchor: String::from(anchor),
+                    kind: String::from(kind),
+                },
+            )
+            .unwrap();
+        }
+        apply(
+            &mut document,
+            &Mutation::AddEdge {
+                from: String::from("B"),
+                to: String::from("C"),
+                anchor: String::new(),
+                kind: String::from("part_of"),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            SOURCE
+                .replace(
+                    "links = [ \"B\" ]\n",
+                    "links = [ \"B\" , \"C\"]\n\n\
+                    [[nodes.A.connections]]\nto = \"D\"\nanchor = \"d\"\n"
+                )
+                .replace(
+                    "anchor = \"Untouched\"\n",
+                    "anchor = \"Untouched\"\n\n\
+                    [[nodes.B.connections]]\nto = \"C\"\nkind = \"part_of\"\n"
+                )
+        );
+    }
+
+    #[test]
+    fn remove_edges() {
+        let mut document = parse(SOURCE).unwrap();
+        for (from, to) in [("A", "B"), ("B", "A")] {
+            apply(
+                &mut document,
+                &Mutation::RemoveEdge {
+                    from: String::from(from),
+                    to: String::from(to),
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            document.to_string(),
+            SOURCE.replace("links = [ \"B\" ]\n", "").replace(
+                "\n[[nodes.B.connections]]\nto = \"A\" # back\n\
+                    anchor = \"Untouched\"\n",
+                ""
+            )
+        );
+
+        let error = apply_to_source(&Mutation::RemoveEdge {
+            from: String::from("A"),
+            to: String::from("C"),
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn set_fields() {
+        let mut document = parse(SOURCE).unwrap();
+        for (field, value) in [
+            ("title", "true"),
+            ("hidden", "true"),
+            ("links", "B C"),
+            ("template", "essay.html"),
+            ("classes", "wide serif"),
+            ("tags", "physics early"),
+        ] {
+            apply(
+                &mut document,
+                &Mutation::SetField {
+                    id: String::from("A"),
+                    field: String::from(field),
+                    value: String::from(value),
+                },
+            )
+            .unwrap();
+        }
+
+        let graph: crate::types::Graph =
+            toml::from_str(&document.to_string()).unwrap();
+        let node = graph.nodes.get("A").unwrap();
+        assert_eq!(node.title, "true");
+        assert!(node.hidden);
+        assert_eq!(node.links, ["B", "C"]);
+        assert_eq!(node.template, "essay.html");
+        assert_eq!(node.classes, ["wide", "serif"]);
+        assert_eq!(node.tags, ["physics", "early"]);
+
+        for (field, value) in [("hidden", "yes"), ("color", "red")] {
+            let error = apply_to_source(&Mutation::SetField {
+                id: String::from("A"),
+                field: String::from(field),
+                value: String::from(value),
+            })
+            .err()
+            .unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn inline_nodes() {
+        let mut document =
+            parse("root_node = \"A\"\nnodes = { A = { text = \"a\" } }")
+                .unwrap();
+        apply(
+            &mut document,
+            &Mutation::AddNode {
+                id: String::from("B"),
+                text: String::from("b"),
+            },
+        )
+        .unwrap();
+        apply(
+            &mut document,
+            &Mutation::AddEdge {
+                from: String::from("A"),
+                to: String::from("B"),
+                anchor: String::from("a"),
+                kind: String::new(),
+            },
+        )
+        .unwrap();
+
+        let graph: crate::types::Graph =
+            toml::from_str(&document.to_string()).unwrap();
+        assert!(graph.nodes.contains_key("B"));
+        let connections = graph.nodes.get("A").unwrap().connections.clone();
+        assert_eq!(connections.unwrap().first().unwrap().to, "B");
+    }
+
+    #[test]
+    fn not_a_table() {
+        let mut document = parse("nodes = 3").unwrap();
+        let error = update_node(&mut document, "A", &NodeFields::default())
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn stale_saves() {
+        let directory = std::env::temp_dir()
+            .join(format!("en-edit-stale-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("graph.toml");
+        fs::write(&path, SOURCE).unwrap();
+
+        // Both forms were loaded before either was saved
+        let graph = modulate_graph(migrate::load(SOURCE));
+        let version = make_version(graph.nodes.get("A"));
+        let update = |text: &str| Mutation::UpdateNode {
+            id: String::from("A"),
+            fields: NodeFields {
+                text: String::from(text),
+                ..NodeFields::default()
+            },
+        };
+
+        save_unchanged(&path, &[update("First")], "A", &version).unwrap();
+        let error = save_unchanged(&path, &[update("Second")], "A", &version)
+            .err()
+            .unwrap();
+        assert!(error.get_ref().unwrap().is::<Stale>());
+        assert!(fs::read_to_string(&path).unwrap().contains("First"));
+        assert_eq!(journal::read(&path).unwrap().len(), 1);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Old entries are dropped so the journal doesn't grow without bound
+const MAX_ENTRIES: usize = 100;
+
+/// A recorded write to the graph file. Only the part of the graph that
+/// changed is kept, along with checksums of the whole graph before and after
+/// the write, so it can be undone as long as nothing else changed the graph.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub time: u64,
+    pub changes: Vec<String>,
+    pub start: usize,
+    pub removed: String,
+    pub inserted: String,
+    pub before_sum: u64,
+    pub after_sum: u64,
+}
+
+impl Entry {
+    pub fn new(changes: Vec<String>, before: &str, after: &str) -> Entry {
+        let start = before
+            .char_indices()
+            .zip(after.chars())
+            .find(|&((_, old), new)| old != new)
+            .map_or(before.len().min(after.len()), |((index, _), _)| index);
+
+        let old_rest = before.get(start..).unwrap_or_default();
+        let new_rest = after.get(start..).unwrap_or_default();
+        let end: usize = old_rest
+            .chars()
+            .rev()
+            .zip(new_rest.chars().rev())
+            .take_while(|&(old, new)| old == new)
+            .map(|(old, _)| old.len_utf8())
+            .sum();
+
+        Entry {
+            time: now(),
+            changes,
+            start,
+            removed: old_rest
+                .get(..old_rest.len().saturating_sub(end))
+                .unwrap_or_default()
+                .to_owned(),
+            inserted: new_rest
+                .get(..new_rest.len().saturating_sub(end))
+                .unwrap_or_default()
+                .to_owned(),
+            before_sum: checksum(before),
+            after_sum: checksum(after),
+        }
+    }
+
+    /// Rebuilds the graph as it was before this entry from `current`, or
+    /// returns `None` if `current` isn't the graph this entry left behind.
+    /// A graph that still matches the one before the entry is returned as
+    /// is, since the write it records never happened.
+    pub fn revert(&self, current: &str) -> Option<String> {
+        let sum = checksum(current);
+        if sum == self.after_sum {
+            let end = self.start.saturating_add(self.inserted.len());
+            Some(format!(
+                "{}{}{}",
+                current.get(..self.start)?,
+                self.removed,
+                current.get(end..)?
+            ))
+        } else if sum == self.before_sum {
+            Some(current.to_owned())
+        } else {
+            None
+        }
+    }
+
+    /// Describes how long ago the entry was recorded, e.g. `3 hours ago`.
+    pub fn make_age(&self) -> String {
+        let seconds = now().saturating_sub(self.time);
+
+        let (amount, unit) = if seconds < 60 {
+            return String::from("just now");
+        } else if seconds < 3600 {
+            (seconds.checked_div(60).unwrap_or_default(), "minute")
+        } else if seconds < 86400 {
+            (seconds.checked_div(3600).unwrap_or_default(), "hour")
+        } else {
+            (seconds.checked_div(86400).unwrap_or_default(), "day")
+        };
+
+        let plural = if amount == 1 { "" } else { "s" };
+        format!("{amount} {unit}{plural} ago")
+    }
+}
+
+/// Holds the advisory lock on a graph file until dropped. The lock is taken
+/// on a sibling `.lock` file, since writes replace the graph file itself.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Waits until no other writer holds the lock on `graph_path`.
+    /// This blocks the thread, so async code should run it, and whatever
+    /// holds the lock, with `spawn_blocking`.
+    ///
+    /// # Errors
+    /// Fails if the lock file can't be opened or locked.
+    pub fn acquire(graph_path: &Path) -> io::Result<Lock> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(sibling(graph_path, "lock"))?;
+        file.lock()?;
+        Ok(Lock { _file: file })
+    }
+}
+
+/// Replaces the file at `path` with `contents` by writing a temporary file
+/// next to it and renaming it over the original, so readers and crashes
+/// never see a partial write.
+///
+/// # Errors
+/// Fails if the temporary file can't be written or renamed.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let temporary = sibling(path, &format!("{}.tmp", std::process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temporary, metadata.permissions())?;
+        }
+        fs::rename(&temporary, path)
+    })();
+
+    if result.is_err() {
+        let _removed = fs::remove_file(&temporary);
+    }
+
+    result
+}
+
+/// Replaces the graph at `graph_path`, which was `before`, with `after`, and
+/// records the write as `changes`. The caller must hold its [`Lock`].
+///
+/// The journal entry is written first, so a crash before the graph is
+/// replaced leaves an entry for a write that never happened, which undoing
+/// drops, rather than a write that can't be undone.
+///
+/// # Errors
+/// Fails if the journal or the graph can't be written.
+pub fn commit(
+    graph_path: &Path,
+    changes: Vec<String>,
+    before: &str,
+    after: &str,
+) -> io::Result<()> {
+    record(graph_path, Entry::new(changes, before, after))?;
+    write_atomic(graph_path, after)
+}
+
+/// Adds `entry` to the journal of the graph at `graph_path`. The caller
+/// must hold its [`Lock`].
+///
+/// # Errors
+/// Fails if the journal can't be read or written.
+pub fn record(graph_path: &Path, entry: Entry) -> io::Result<()> {
+    let mut entries = read(graph_path)?;
+    entries.push(entry);
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    write(graph_path, entries.get(excess..).unwrap_or_default())
+}
+
+/// Returns the journal of the graph at `graph_path`, oldest entry first.
+///
+/// # Errors
+/// Fails if the journal exists but can't be read or parsed.
+pub fn read(graph_path: &Path) -> io::Result<Vec<Entry>> {
+    let source = match fs::read_to_string(sibling(graph_path, "journal")) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+/// Restores the graph at `graph_path` to how it was before its last `count`
+/// recorded writes, newest first, and removes them from the journal.
+/// Returns the undone entries.
+///
+/// # Errors
+/// Fails if there are fewer than `count` entries, if the graph was changed
+/// by something other than en since an entry was recorded, or if the graph
+/// or journal can't be read or written.
+pub fn undo(graph_path: &Path, count: usize) -> io::Result<Vec<Entry>> {
+    let _lock = Lock::acquire(graph_path)?;
+    let mut entries = read(graph_path)?;
+
+    if count > entries.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Can't undo {count} edits, the journal only has {}",
+                entries.len()
+            ),
+        ));
+    }
+
+    let mut undone = vec![];
+    for _ in 0..count {
+        let Some(entry) = entries.pop() else { break };
+
+        let restored = match fs::read_to_string(graph_path) {
+            Ok(current) => match entry.revert(&current) {
+                Some(before) => write_atomic(graph_path, &before),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} changed since \"{}\" was recorded, not undoing it",
+                        graph_path.display(),
+                        entry.changes.join(", ")
+                    ),
+                )),
+            },
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = restored {
+            // The entry wasn't undone, so it stays, while the journal is
+            // kept in step with the edits already undone
+            entries.push(entry);
+            write(graph_path, &entries)?;
+            return Err(e);
+        }
+        undone.push(entry);
+    }
+
+    write(graph_path, &entries)?;
+    Ok(undone)
+}
+
+fn write(graph_path: &Path, entries: &[Entry]) -> io::Result<()> {
+    let lines = entries
+        .iter()
+        .map(|entry| serde_json::to_string(entry).map_err(io::Error::other))
+        .collect::<io::Result<Vec<String>>>()?;
+
+    let mut contents = lines.join("\n");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+
+    write_atomic(&sibling(graph_path, "journal"), &contents)
+}
+
+// FNV-1a, which unlike the standard library's hasher doesn't change between
+// Rust versions, so entries can still be undone after upgrading en
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    path.with_added_extension(extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_graph(name: &str, contents: &str) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("en-journal-{name}-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("graph.toml");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn edit(path: &Path, contents: &str) {
+        let _lock = Lock::acquire(path).unwrap();
+        let before = fs::read_to_string(path).unwrap();
+        commit(path, vec![format!("write {contents}")], &before, contents)
+            .unwrap();
+    }
+
+    #[test]
+    fn record_and_undo() {
+        let path = make_graph("undo", "0");
+        edit(&path, "1");
+        edit(&path, "2");
+        edit(&path, "3");
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries.first().unwrap().changes, ["write 1"]);
+
+        let undone = undo(&path, 2).unwrap();
+        assert_eq!(undone.len(), 2);
+        assert_eq!(undone.first().unwrap().changes, ["write 3"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1");
+        assert_eq!(read(&path).unwrap().len(), 1);
+
+        let too_many = undo(&path, 2).err().unwrap();
+        assert_eq!(too_many.kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn changed_outside() {
+        let path = make_graph("outside", "0");
+        edit(&path, "1");
+        edit(&path, "2");
+        fs::write(&path, "changed").unwrap();
+
+        let error = undo(&path, 1).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "changed");
+        assert_eq!(read(&path).unwrap().len(), 2);
+
+        // Once the change is reverted, the kept entry can still be undone
+        fs::write(&path, "2").unwrap();
+        assert_eq!(undo(&path, 1).unwrap().len(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1");
+        assert_eq!(read(&path).unwrap().len(), 1);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn interrupted_write() {
+        let path = make_graph("interrupted", "0");
+        edit(&path, "1");
+
+        // As if en stopped between recording an edit and writing it
+        record(&path, Entry::new(vec![String::from("lost")], "1", "2"))
+            .unwrap();
+
+        let undone = undo(&path, 2).unwrap();
+        assert_eq!(undone.first().unwrap().changes, ["lost"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0");
+        assert!(read(&path).unwrap().is_empty());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn changed_part() {
+        let before = format!("{}café\n{}", "a".repeat(500), "b".repeat(500));
+        let after = before.replace("café", "cafè au lait");
+        let entry = Entry::new(vec![], &before, &after);
+
+        assert_eq!(entry.start, 503);
+        assert_eq!(entry.removed, "é");
+        assert_eq!(entry.inserted, "è au lait");
+        assert_eq!(entry.revert(&after).unwrap(), before);
+        assert_eq!(entry.revert(&before).unwrap(), before);
+        assert!(entry.revert("something else").is_none());
+    }
+
+    #[test]
+    fn entry_limit() {
+        let path = make_graph("limit", "0");
+        for i in 0..=MAX_ENTRIES {
+            edit(&path, &i.to_string());
+        }
+
+        let entries = read(&path).unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries.first().unwrap().changes, ["write 1"]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn atomic_write() {
+        let path = make_graph("atomic", "old");
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        let files = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(files, 1);
+
+        let missing = Path::new("/nonexistent-Xs1bT4qP/graph.toml");
+        assert!(write_atomic(missing, "new").is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn age() {
+        let entry = Entry::new(vec![], "", "");
+        assert_eq!(entry.make_age(), "just now");
+
+        let old = Entry {
+            time: entry.time.saturating_sub(7300),
+            ..entry
+        };
+        assert_eq!(old.make_age(), "2 hours ago");
+    }
+}
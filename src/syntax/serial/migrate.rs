@@ -0,0 +1,287 @@
+use std::{fmt, fs, io, path::Path, sync::Once};
+
+use toml_edit::{DocumentMut, Item, Value};
+
+use crate::{
+    prelude::*,
+    syntax::serial::{deserialize_graph, edit, journal, Format},
+    types::{Graph, Meta},
+};
+
+pub type Version = (u8, u8, u8);
+
+/// The newest graph format version this build of en understands. Graphs
+/// without a version are treated as `0.0.0`.
+pub const CURRENT: Version = (0, 1, 0);
+
+/// An upgrade of graph sources from one format version to the next.
+pub struct Migration {
+    pub from: Version,
+    pub to: Version,
+    pub description: &'static str,
+    apply: fn(&mut DocumentMut) -> io::Result<()>,
+}
+
+impl fmt::Display for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "migration from {} to {} ({})",
+            describe(self.from),
+            describe(self.to),
+            self.description
+        )
+    }
+}
+
+// Ordered by version, each starting where the previous one ends
+static MIGRATIONS: [Migration; 1] = [Migration {
+    from: (0, 0, 0),
+    to: (0, 1, 0),
+    description: "record the format version",
+    // Unversioned graphs already match the first versioned format
+    apply: |_document| Ok(()),
+}];
+
+static NEWER_WARNING: Once = Once::new();
+
+/// Deserializes a graph TOML, upgrading it in memory if it's older than
+/// [`CURRENT`] and warning if it's newer.
+pub fn load(source: &str) -> Graph {
+    let graph = deserialize_graph(&Format::TOML, source);
+
+    if !graph.meta.messages.is_empty() || graph.meta.version == CURRENT {
+        return graph;
+    }
+
+    // Only a warning, so it's kept out of `meta.messages`, which are errors
+    if let Some(message) = warn_newer(graph.meta.version) {
+        NEWER_WARNING.call_once(|| log!("{message}"));
+        return graph;
+    }
+
+    let upgraded = edit::parse(source).and_then(|mut document| {
+        upgrade(&mut document)?;
+        Ok(document.to_string())
+    });
+
+    match upgraded {
+        Ok(upgraded_source) => {
+            deserialize_graph(&Format::TOML, &upgraded_source)
+        },
+        Err(e) => with_message(graph, format!("Failed to migrate graph: {e}")),
+    }
+}
+
+/// Describes why a graph of `version` may not be read in full, if it's
+/// newer than [`CURRENT`].
+pub fn warn_newer(version: Version) -> Option<String> {
+    (version > CURRENT).then(|| {
+        format!(
+            "Graph version {} is newer than {}, the latest this version of \
+                en understands, so parts of it may be ignored",
+            describe(version),
+            describe(CURRENT)
+        )
+    })
+}
+
+/// Applies every migration needed to bring `document` up to [`CURRENT`],
+/// updating its version after each one. Returns the applied migrations.
+///
+/// # Errors
+/// Fails if the version isn't valid or a migration fails.
+pub fn upgrade(
+    document: &mut DocumentMut,
+) -> io::Result<Vec<&'static Migration>> {
+    let mut applied = vec![];
+
+    loop {
+        let version = read_version(document)?;
+        let Some(migration) = MIGRATIONS.iter().find(|m| m.to > version) else {
+            break;
+        };
+
+        (migration.apply)(document)?;
+        write_version(document, migration.to)?;
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+/// Upgrades the graph file at `path` to [`CURRENT`] in place, through the
+/// same locked, journaled writes as edits. Returns the applied migrations.
+///
+/// # Errors
+/// Fails if the graph is newer than [`CURRENT`], if a migration fails or if
+/// the file can't be locked, read or written.
+pub fn rewrite(path: &Path) -> io::Result<Vec<&'static Migration>> {
+    let _lock = journal::Lock::acquire(path)?;
+    let before = fs::read_to_string(path)?;
+    let mut document = edit::parse(&before)?;
+
+    let version = read_version(&document)?;
+    if version > CURRENT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} has version {}, which is newer than {}",
+                path.display(),
+                describe(version),
+                describe(CURRENT)
+            ),
+        ));
+    }
+
+    let applied = upgrade(&mut document)?;
+    if applied.is_empty() {
+        return Ok(applied);
+    }
+
+    let after = document.to_string();
+    journal::commit(
+        path,
+        applied.iter().map(ToString::to_string).collect(),
+        &before,
+        &after,
+    )?;
+
+    Ok(applied)
+}
+
+/// Reads `meta.version` from a graph document.
+///
+/// # Errors
+/// Fails if the version isn't an array of three numbers from 0 to 255.
+pub fn read_version(document: &DocumentMut) -> io::Result<Version> {
+    let Some(item) = document.get("meta").and_then(|meta| meta.get("version"))
+    else {
+        return Ok((0, 0, 0));
+    };
+
+    let parts: Option<Vec<u8>> = item.as_array().map(|array| {
+        array
+            .iter()
+            .filter_map(|part| u8::try_from(part.as_integer()?).ok())
+            .collect()
+    });
+
+    match parts.as_deref() {
+        Some(&[major, minor, patch]) => Ok((major, minor, patch)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid graph version {}", item.to_string().trim()),
+        )),
+    }
+}
+
+pub fn describe(version: Version) -> String {
+    format!("{}.{}.{}", version.0, version.1, version.2)
+}
+
+fn write_version(
+    document: &mut DocumentMut,
+    version: Version,
+) -> io::Result<()> {
+    let meta = document
+        .entry("meta")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "meta is not a table")
+        })?;
+
+    // `[meta]` only has a header if it holds values of its own
+    meta.set_implicit(false);
+
+    let array = Value::Array(
+        [version.0, version.1, version.2]
+            .into_iter()
+            .map(i64::from)
+            .collect(),
+    );
+    if let Some(existing) = meta.get_mut("version").and_then(Item::as_value_mut)
+    {
+        let decor = existing.decor().clone();
+        *existing = array;
+        *existing.decor_mut() = decor;
+    } else {
+        meta.insert("version", Item::Value(array));
+    }
+
+    Ok(())
+}
+
+fn with_message(graph: Graph, message: String) -> Graph {
+    let mut messages = graph.meta.messages.clone();
+    messages.push(message);
+
+    Graph {
+        meta: Meta {
+            messages,
+            ..graph.meta
+        },
+        ..graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned() {
+        let source = "root_node = \"A\"\n\n\
+            [nodes.A]\ntext = \"\"\n\n\
+            # Site settings\n\
+            [meta.config]\ntree = false\n";
+        let mut document = edit::parse(source).unwrap();
+
+        assert_eq!(read_version(&document).unwrap(), (0, 0, 0));
+        let applied = upgrade(&mut document).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(read_version(&document).unwrap(), CURRENT);
+        assert_eq!(
+            document.to_string(),
+            "root_node = \"A\"\n\n\
+            [nodes.A]\ntext = \"\"\n\n\
+            [meta]\nversion = [0, 1, 0]\n\n\
+            # Site settings\n\
+            [meta.config]\ntree = false\n"
+        );
+        assert!(upgrade(&mut document).unwrap().is_empty());
+
+        let graph = load(source);
+        assert_eq!(graph.meta.version, CURRENT);
+        assert!(!graph.meta.config.tree);
+    }
+
+    #[test]
+    fn newer() {
+        let graph = load(
+            "root_node = \"A\"\n[nodes.A]\ntext = \"\"\n\
+            [meta]\nversion = [255, 0, 0]\n",
+        );
+        assert_eq!(graph.meta.version, (255, 0, 0));
+        assert!(graph.meta.messages.is_empty());
+        assert!(warn_newer(graph.meta.version).unwrap().contains("newer"));
+        assert!(warn_newer(CURRENT).is_none());
+    }
+
+    #[test]
+    fn invalid_version() {
+        let document = edit::parse("[meta]\nversion = [0, 300, 0]\n").unwrap();
+        let error = read_version(&document).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid graph version [0, 300, 0]");
+    }
+
+    #[test]
+    fn migration_names() {
+        let migration = MIGRATIONS.first().unwrap();
+        assert_eq!(
+            migration.to_string(),
+            "migration from 0.0.0 to 0.1.0 (record the format version)"
+        );
+    }
+}
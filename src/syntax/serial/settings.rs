@@ -0,0 +1,303 @@
+use std::{fs, io, path::Path, sync::OnceLock};
+
+use crate::{prelude::*, syntax::command::Arguments, types::Config};
+
+const DEFAULT_PATH: &str = "./en.toml";
+const PREFIX: &str = "EN_";
+
+// The settings en was started with, read once so that a running server
+// keeps them even if the settings file is changed or broken later
+static STARTUP: OnceLock<Result<Vec<Override>, String>> = OnceLock::new();
+
+/// A value for a setting, either already typed, as read from a settings
+/// file, or as text to be read as the type of the setting.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Typed(toml::Value),
+    Text(String),
+}
+
+/// A setting to apply over the config in the graph, and where it came from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Override {
+    pub origin: String,
+    pub key: String,
+    pub value: Value,
+}
+
+/// Applies settings over `config`, from lowest to highest precedence:
+/// the settings file, `EN_*` environment variables and `--set` arguments.
+///
+/// # Errors
+/// Fails if the settings file can't be read or parsed, or if a setting
+/// doesn't exist or its value isn't of the right type.
+pub fn load(config: Config, args: &Arguments) -> Result<Config, String> {
+    apply(config, &read_all(args)?)
+}
+
+/// Applies the settings en was started with over `config`. They're read
+/// once, when [`validate`] checks them or else on first use.
+///
+/// # Errors
+/// Fails for the same reasons as [`load`].
+pub fn layer(config: Config) -> Result<Config, String> {
+    let startup = STARTUP.get_or_init(|| read_all(&Arguments::new().parse()));
+    match *startup {
+        Ok(ref overrides) => apply(config, overrides),
+        Err(ref e) => Err(e.clone()),
+    }
+}
+
+/// Checks that the settings given to en can be applied, logging any `EN_*`
+/// environment variables that don't name a setting, and keeps them for
+/// [`layer`].
+///
+/// # Errors
+/// Fails for the same reasons as [`load`].
+pub fn validate(args: &Arguments) -> Result<(), String> {
+    let keys = list_keys();
+    for (name, _) in std::env::vars() {
+        if let Some(key) = name.strip_prefix(PREFIX)
+            && !keys.contains(&key.to_lowercase())
+        {
+            log!("Ignored {name}, which doesn't name a setting");
+        }
+    }
+
+    let overrides = read_all(args)?;
+    apply(Config::default(), &overrides)?;
+    STARTUP.get_or_init(|| Ok(overrides));
+    Ok(())
+}
+
+// Reads the settings file, environment and arguments, in that order
+fn read_all(args: &Arguments) -> Result<Vec<Override>, String> {
+    let mut overrides = read_file(args.config_path.as_deref())?;
+    overrides.extend(read_variables(std::env::vars()));
+    overrides.extend(read_arguments(&args.settings));
+    Ok(overrides)
+}
+
+/// Applies `overrides` in order over `config`.
+///
+/// # Errors
+/// Fails if a setting doesn't exist or its value isn't of the right type.
+pub fn apply(config: Config, overrides: &[Override]) -> Result<Config, String> {
+    let mut table = toml::Table::try_from(config).map_err(|e| e.to_string())?;
+
+    for setting in overrides {
+        let Some(current) = table.get(&setting.key) else {
+            return Err(format!(
+                "Unknown setting {} from {}",
+                setting.key, setting.origin
+            ));
+        };
+
+        let value = match setting.value {
+            Value::Typed(ref value) => value.clone(),
+            Value::Text(ref text) => {
+                read_text(current, text).ok_or_else(|| {
+                    format!(
+                        "Invalid value {text:?} for {} from {}, expected {}",
+                        setting.key,
+                        setting.origin,
+                        current.type_str()
+                    )
+                })?
+            },
+        };
+
+        if value.type_str() != current.type_str() {
+            return Err(format!(
+                "Invalid value {value} for {} from {}, expected {}",
+                setting.key,
+                setting.origin,
+                current.type_str()
+            ));
+        }
+
+        table.insert(setting.key.clone(), value);
+    }
+
+    table
+        .try_into()
+        .map_err(|e: toml::de::Error| e.message().to_owned())
+}
+
+/// Reads a settings file holding the same keys as `[meta.config]`. Without
+/// a path, `./en.toml` is read if it exists.
+///
+/// # Errors
+/// Fails if the file can't be read or parsed.
+pub fn read_file(path: Option<&Path>) -> Result<Vec<Override>, String> {
+    let (file, read) = match path {
+        Some(given) => (given, fs::read_to_string(given)),
+        None => {
+            let default = Path::new(DEFAULT_PATH);
+            match fs::read_to_string(default) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    return Ok(vec![]);
+                },
+                read => (default, read),
+            }
+        },
+    };
+
+    let origin = file.display().to_string();
+    let source = read.map_err(|e| format!("Failed to read {origin}: {e}"))?;
+    let table: toml::Table = toml::from_str(&source)
+        .map_err(|e| format!("Failed to parse {origin}: {}", e.message()))?;
+
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| Override {
+            origin: origin.clone(),
+            key,
+            value: Value::Typed(value),
+        })
+        .collect())
+}
+
+/// Reads `EN_*` variables naming a setting, e.g. `EN_SITE_TITLE` for
+/// `site_title`, ignoring any others.
+pub fn read_variables<I>(variables: I) -> Vec<Override>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let keys = list_keys();
+
+    variables
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix(PREFIX)?.to_lowercase();
+            keys.contains(&key).then(|| Override {
+                origin: name.clone(),
+                key,
+                value: Value::Text(value),
+            })
+        })
+        .collect()
+}
+
+pub fn read_arguments(settings: &[(String, String)]) -> Vec<Override> {
+    settings
+        .iter()
+        .map(|setting| Override {
+            origin: format!("--set {}", setting.0),
+            key: setting.0.clone(),
+            value: Value::Text(setting.1.clone()),
+        })
+        .collect()
+}
+
+fn list_keys() -> Vec<String> {
+    toml::Table::try_from(Config::default())
+        .map(|table| table.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+// Reads text as the same type as the current value of a setting
+fn read_text(current: &toml::Value, text: &str) -> Option<toml::Value> {
+    match *current {
+        toml::Value::String(_) => Some(toml::Value::String(text.to_owned())),
+        toml::Value::Boolean(_) => text.parse().ok().map(toml::Value::Boolean),
+        toml::Value::Integer(_) => text.parse().ok().map(toml::Value::Integer),
+        toml::Value::Float(_)
+        | toml::Value::Datetime(_)
+        | toml::Value::Array(_)
+        | toml::Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::serial::populate_graph;
+
+    use super::*;
+
+    fn set(key: &str, value: &str) -> (String, String) {
+        (key.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn precedence() {
+        let config = populate_graph().meta.config;
+        assert!(config.tree);
+
+        let file = Override {
+            origin: String::from("en.toml"),
+            key: String::from("site_title"),
+            value: Value::Typed(toml::Value::String(String::from("File"))),
+        };
+        let mut overrides = vec![file];
+        overrides.extend(read_variables([
+            set("EN_SITE_TITLE", "Environment"),
+            set("EN_TREE", "false"),
+            set("EN_INDEX_NODE_COUNT", "3"),
+            set("HOME", "/root"),
+        ]));
+        overrides.extend(read_arguments(&[set("site_title", "Argument")]));
+
+        let layered = apply(config.clone(), &overrides).unwrap();
+        assert_eq!(layered.site_title, "Argument");
+        assert!(!layered.tree);
+        assert_eq!(layered.index_node_count, 3);
+        assert_eq!(layered.content_language, config.content_language);
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let unknown =
+            apply(Config::default(), &read_arguments(&[set("colour", "blue")]));
+        assert_eq!(
+            unknown.err().unwrap(),
+            "Unknown setting colour from --set colour"
+        );
+
+        let not_bool =
+            apply(Config::default(), &read_variables([set("EN_TREE", "yes")]));
+        assert_eq!(
+            not_bool.err().unwrap(),
+            "Invalid value \"yes\" for tree from EN_TREE, expected boolean"
+        );
+
+        let typed = Override {
+            origin: String::from("en.toml"),
+            key: String::from("tree"),
+            value: Value::Typed(toml::Value::Integer(1)),
+        };
+        assert_eq!(
+            apply(Config::default(), &[typed]).err().unwrap(),
+            "Invalid value 1 for tree from en.toml, expected boolean"
+        );
+
+        let too_large = apply(
+            Config::default(),
+            &read_arguments(&[set("index_node_count", "70000")]),
+        );
+        assert!(too_large.is_err());
+    }
+
+    #[test]
+    fn settings_file() {
+        assert!(read_file(None).unwrap().is_empty());
+
+        let missing = read_file(Some(Path::new("nonexistent-Lq2wX9.toml")));
+        assert!(missing.err().unwrap().starts_with("Failed to read"));
+
+        let path = std::env::temp_dir()
+            .join(format!("en-settings-{}.toml", std::process::id()));
+        fs::write(&path, "site_title = \"Staging\"\ntree = false\n").unwrap();
+        let overrides = read_file(Some(&path)).unwrap();
+        let config = apply(Config::default(), &overrides).unwrap();
+        assert_eq!(config.site_title, "Staging");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn startup_settings() {
+        let args = Arguments::new().parse();
+        validate(&args).unwrap();
+        assert_eq!(layer(Config::default()), load(Config::default(), &args));
+    }
+}
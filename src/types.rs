@@ -1,16 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Serializer, Deserialize};
 
 use crate::syntax::content;
 
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
 pub struct Graph {
+    #[serde(serialize_with = "serialize_sorted")]
     pub nodes: HashMap<String, Node>,
     pub root_node: String,
-    #[serde(skip_deserializing)]
+    #[serde(
+        skip_deserializing,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     pub incoming: HashMap<String, Vec<Edge>>,
-    #[serde(skip_deserializing)]
+    #[serde(
+        skip_deserializing,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     pub lowercase_keymap: HashMap<String, String>,
     #[serde(default)]
     pub meta: Meta,
@@ -27,6 +36,14 @@ pub struct Node {
     pub id: String,
     #[serde(default)]
     pub hidden: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub summary: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub template: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<String>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub connections: Option<Vec<Edge>>,
@@ -39,12 +56,15 @@ pub struct Edge {
     pub anchor: String,
     #[serde(default)]
     pub from: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub kind: String,
     #[serde(default)]
     pub detached: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
 pub struct Meta {
+    #[serde(default)]
     pub config: Config,
     #[serde(default = "mkversion")]
     pub version: (u8, u8, u8),
@@ -52,6 +72,14 @@ pub struct Meta {
     pub messages: Vec<String>,
 }
 
+// Written in key order, so the same graph always serializes the same way
+fn serialize_sorted<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
 // See: https://github.com/serde-rs/serde/issues/368
 fn mkversion() -> (u8, u8, u8) {
     (0, 0, 0)
@@ -98,6 +126,12 @@ pub struct Config {
     pub ascii_dom_ids: bool,
     #[serde(default)]
     pub content_language: String,
+    #[serde(default = "mkfalse")]
+    pub edit: bool,
+    #[serde(default = "mktrue")]
+    pub api: bool,
+    #[serde(default)]
+    pub theme: String,
 }
 
 // See: https://github.com/serde-rs/serde/issues/368
@@ -139,6 +173,9 @@ impl Graph {
                     tree_node_text: false,
                     ascii_dom_ids: false,
                     content_language: String::new(),
+                    edit: false,
+                    api: true,
+                    theme: String::new(),
                 },
                 version: (0, 1, 0),
                 messages: message.map_or(vec![], |m| vec![m.to_string()]),
@@ -158,6 +195,24 @@ impl Graph {
     pub fn get_root(&self) -> Option<Node> {
         self.nodes.get(&self.root_node).cloned()
     }
+
+    /// Nodes whose ID, title or text contain `query`, ignoring case, ordered
+    /// by ID. Hidden nodes are only included with `include_hidden`.
+    pub fn search(&self, query: &str, include_hidden: bool) -> Vec<&Node> {
+        let lowercase_query = query.to_lowercase();
+        let mut found: Vec<&Node> = self
+            .nodes
+            .values()
+            .filter(|node| include_hidden || !node.hidden)
+            .filter(|node| {
+                [&node.id, &node.title, &node.text].iter().any(|field| {
+                    field.to_lowercase().contains(&lowercase_query)
+                })
+            })
+            .collect();
+        found.sort_by(|a, b| a.id.cmp(&b.id));
+        found
+    }
 }
 
 impl Node {
@@ -172,6 +227,20 @@ impl Node {
             connections: None,
             links: vec![],
             hidden: false,
+            summary: String::new(),
+            tags: vec![],
+            template: String::new(),
+            classes: vec![],
+        }
+    }
+
+    /// Returns the explicit summary if set, otherwise a plain-text rendering
+    /// of the first paragraph of the node's text.
+    pub fn make_summary(&self) -> String {
+        if self.summary.is_empty() {
+            content::summarize(&self.text)
+        } else {
+            self.summary.clone()
         }
     }
 }
@@ -205,6 +274,23 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn search() {
+        let graph = populate_graph();
+
+        let found: Vec<&str> = graph
+            .search("tOmL", false)
+            .iter()
+            .map(|node| node.id.as_str())
+            .collect();
+        assert!(found.contains(&"TOML"));
+        assert!(found.contains(&"Documentation"));
+        assert!(!found.contains(&"Test"));
+
+        assert!(graph.search("Test", false).iter().all(|node| !node.hidden));
+        assert!(graph.search("Test", true).iter().any(|node| node.hidden));
+    }
+
     #[test]
     fn empty_graph() {
         let graph = Graph::new(Some("ISryQFd9peG6eYz9CFRQFWeD1GnPo0oj"));
@@ -222,6 +308,41 @@ mod tests {
         assert_eq!(node.text, "Node is empty, missing or wasn't found.");
     }
 
+    #[test]
+    fn explicit_summary() {
+        let payload = "u4Xk0Cq9lA2XfJkYSpz1d8bYVv3bQy7R";
+        let node = Node {
+            summary: payload.to_string(),
+            ..Node::new(Some(String::from("Not the summary.")))
+        };
+        assert_eq!(node.make_summary(), payload);
+    }
+
+    #[test]
+    fn unset_summary() {
+        let node = Node::new(Some(String::from("Text.")));
+        assert!(!toml::to_string(&node).unwrap().contains("summary"));
+        assert!(!toml::to_string(&node).unwrap().contains("tags"));
+
+        let summarized = Node {
+            summary: String::from("Short."),
+            ..node
+        };
+        assert!(
+            toml::to_string(&summarized)
+                .unwrap()
+                .contains("summary = \"Short.\"")
+        );
+    }
+
+    #[test]
+    fn derived_summary() {
+        let node = Node::new(Some(String::from(
+            "## Header\nFirst |anchor|Node `code`.\n\nSecond paragraph.",
+        )));
+        assert_eq!(node.make_summary(), "First anchor code.");
+    }
+
     #[test]
     fn empty_footer_text() {
         let default_graph = populate_graph();